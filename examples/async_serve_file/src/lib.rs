@@ -1,41 +1,42 @@
-use bstr::BString;
-use haproxy_api::{Core, ServiceMode};
+use haproxy_api::{AsyncRequest, Core, ResponseSink};
 use mlua::prelude::*;
-use tokio::fs;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
 
 #[mlua::lua_module(skip_memory_check)]
 fn haproxy_async_module(lua: &Lua) -> LuaResult<bool> {
     let core = Core::new(lua)?;
 
-    // It's important to use `create_async_function` from the haproxy_api
-    let get_file = haproxy_api::create_async_function(lua, |path: String| async move {
-        match fs::read(&path).await {
-            Ok(content) => Ok((Some(BString::from(content)), None)),
-            Err(err) => Ok((None, Some(err.to_string()))),
-        }
-    })?;
+    // Files are streamed in chunks, without reading them into memory
+    core.register_async_service(
+        "serve_file",
+        |req: AsyncRequest, mut resp: ResponseSink| async move {
+            // Strip first '/'
+            let path = req.path.strip_prefix('/').unwrap_or(&req.path);
+            let mut file = match File::open(path).await {
+                Ok(file) => file,
+                Err(err) => {
+                    resp.set_status(404, None);
+                    resp.add_header("content-type", "text/plain");
+                    return resp.send(format!("{err}\n")).await;
+                }
+            };
+            let length = file.metadata().await.into_lua_err()?.len();
 
-    let code = mlua::chunk! {
-        local applet = ...
-        // Strip first '/'
-        local response, err = $get_file(string.sub(applet.path, 2))
-        if err ~= nil then
-            err = err.."\n"
-            applet:set_status(404)
-            applet:add_header("content-length", string.len(err))
-            applet:add_header("content-type", "text/plain")
-            applet:start_response()
-            applet:send(err)
-            return
-        end
+            resp.add_header("content-length", length.to_string());
+            resp.add_header("content-type", "application/octet-stream");
+            resp.start_response().await?;
 
-        applet:set_status(200)
-        applet:add_header("content-length", string.len(response))
-        applet:add_header("content-type", "application/octet-stream")
-        applet:start_response()
-        applet:send(response)
-    };
-    core.register_lua_service("serve_file", ServiceMode::Http, code)?;
+            let mut buf = vec![0; 16384];
+            loop {
+                let n = file.read(&mut buf).await.into_lua_err()?;
+                if n == 0 {
+                    return Ok(());
+                }
+                resp.send(&buf[..n]).await?;
+            }
+        },
+    )?;
 
     Ok(true)
 }
@@ -1,16 +1,17 @@
 use std::io::Write;
 
 use brotlic::{BrotliEncoderOptions, CompressorWriter, Quality, WindowSize};
-use haproxy_api::{Core, FilterMethod, FilterResult, Headers, HttpMessage, Txn, UserFilter};
+use haproxy_api::{BodyFilter, BodyTransformer, Core, HttpMessage, Txn};
 use mlua::prelude::*;
 
 #[derive(Default)]
 struct BrotliFilter {
     enabled: bool,
-    writer: Option<CompressorWriter<Vec<u8>>>,
     options: BrotliFilterOptions,
 }
 
+struct BrotliEncoder(Option<CompressorWriter<Vec<u8>>>);
+
 #[derive(Debug, Clone, mlua::FromLua)]
 struct BrotliFilterOptions {
     quality: u8,
@@ -33,11 +34,11 @@ impl Default for BrotliFilterOptions {
 }
 
 impl BrotliFilter {
-    fn process_request_headers(&mut self, txn: Txn, msg: HttpMessage) -> LuaResult<()> {
+    fn process_request_headers(&mut self, txn: Txn, msg: &HttpMessage) -> LuaResult<()> {
         // Check if we can prefer brotli over other encodings
         // We support only GET method
-        self.enabled = txn.f.get::<String>("method", ())? == "GET"
-            && Self::prefer_brotli_encoding(msg.get_headers()?)?;
+        self.enabled = txn.f.method()?.as_deref() == Some("GET")
+            && msg.get_headers()?.prefers("accept-encoding", "br")?;
 
         if self.enabled && self.options.offload {
             msg.del_header("accept-encoding")?;
@@ -46,20 +47,24 @@ impl BrotliFilter {
         Ok(())
     }
 
-    fn process_response_headers(&mut self, lua: &Lua, txn: Txn, msg: HttpMessage) -> LuaResult<()> {
+    fn process_response_headers(
+        &mut self,
+        txn: Txn,
+        msg: &HttpMessage,
+    ) -> LuaResult<Option<BrotliEncoder>> {
         // We encode only "200" responses
-        if !self.enabled || txn.f.get::<u16>("status", ())? != 200 {
-            return Ok(());
+        if !self.enabled || txn.f.status()? != Some(200) {
+            return Ok(None);
         }
 
         let headers = msg.get_headers()?;
         // Do not encode when `content-encoding` already present
-        let mut skip_encoding = headers.get_first::<LuaValue>("content-encoding")?.is_some();
+        let mut skip_encoding = headers.contains("content-encoding")?;
         // Do not encode when `cache-control` includes `no-transform`
         skip_encoding |= headers
-            .get::<String>("cache-control")?
+            .get_list("cache-control")?
             .iter()
-            .any(|v| v.contains("no-transform"));
+            .any(|v| v.eq_ignore_ascii_case(b"no-transform"));
         // Check content type
         if !skip_encoding {
             let content_type = headers
@@ -79,16 +84,7 @@ impl BrotliFilter {
             }
         }
         if skip_encoding {
-            return Ok(());
-        }
-
-        // Update ETag
-        match headers.get::<String>("etag")? {
-            etag if etag.len() > 1 => return Ok(()),
-            etag if etag.len() == 1 && etag[0].starts_with('"') => {
-                msg.set_header("etag", format!("W/{}", etag[0]))?;
-            }
-            _ => {}
+            return Ok(None);
         }
 
         let size_hint = headers
@@ -104,43 +100,13 @@ impl BrotliFilter {
             .size_hint(size_hint)
             .build()
             .expect("Failed to build brotli encoder");
-        self.writer = Some(CompressorWriter::with_encoder(encoder, buf));
+        let writer = CompressorWriter::with_encoder(encoder, buf);
 
         // Update response headers
         msg.set_header("content-encoding", "br")?;
         msg.add_header("vary", "accept-encoding")?;
-        // switch to chunked transfer encoding
-        msg.set_body_len(None)?;
 
-        Self::register_data_filter(lua, txn, msg.channel()?)
-    }
-
-    fn prefer_brotli_encoding(headers: Headers) -> LuaResult<bool> {
-        let accept_encoding = headers.get::<String>("accept-encoding")?;
-        let vals = accept_encoding
-            .iter()
-            .flat_map(|v| v.split(',').map(str::trim))
-            .filter_map(|v| {
-                let (enc, qval) = match v.split_once(";q=") {
-                    Some((e, q)) => (e, q),
-                    None => return Some((v, 1.0f32)),
-                };
-                let qval = match qval.parse::<f32>() {
-                    Ok(f) if f <= 1.0 => f, // q-values over 1 are unacceptable,
-                    _ => return None,
-                };
-                Some((enc, qval))
-            });
-
-        let (mut preferred_encoding, mut max_qval) = ("", 0.);
-        for (enc, qval) in vals {
-            if qval > max_qval {
-                (preferred_encoding, max_qval) = (enc, qval);
-            } else if qval == max_qval && enc == "br" {
-                preferred_encoding = "br";
-            }
-        }
-        Ok(preferred_encoding == "br")
+        Ok(Some(BrotliEncoder(Some(writer))))
     }
 
     fn parse_args(args: LuaTable) -> LuaResult<BrotliFilterOptions> {
@@ -167,14 +133,8 @@ impl BrotliFilter {
                     options.quality = quality;
                 }
                 arg if arg.starts_with("window:") => {
-                    let mut window = arg[7..].trim().parse::<u8>().unwrap_or_default();
-                    if window < 10 {
-                        window = 10;
-                    }
-                    if window > 24 {
-                        window = 24;
-                    }
-                    options.window = window;
+                    let window = arg[7..].trim().parse::<u8>().unwrap_or_default();
+                    options.window = window.clamp(10, 24);
                 }
                 _ => {}
             }
@@ -184,8 +144,8 @@ impl BrotliFilter {
     }
 }
 
-impl UserFilter for BrotliFilter {
-    const METHODS: u8 = FilterMethod::HTTP_HEADERS | FilterMethod::HTTP_PAYLOAD;
+impl BodyFilter for BrotliFilter {
+    type Transformer = BrotliEncoder;
 
     fn new(_: &Lua, args: LuaTable) -> LuaResult<Self> {
         Ok(BrotliFilter {
@@ -194,44 +154,37 @@ impl UserFilter for BrotliFilter {
         })
     }
 
-    fn http_headers(&mut self, lua: &Lua, txn: Txn, msg: HttpMessage) -> LuaResult<FilterResult> {
+    fn http_headers(
+        &mut self,
+        _: &Lua,
+        txn: Txn,
+        msg: &HttpMessage,
+    ) -> LuaResult<Option<BrotliEncoder>> {
         if !msg.is_resp()? {
             self.process_request_headers(txn, msg)?;
-        } else {
-            self.process_response_headers(lua, txn, msg)?;
+            return Ok(None);
         }
-        Ok(FilterResult::Continue)
+        self.process_response_headers(txn, msg)
     }
+}
 
-    fn http_payload(&mut self, _: &Lua, _: Txn, msg: HttpMessage) -> LuaResult<Option<usize>> {
-        if let Some(chunk) = msg.body(None, Some(-1))? {
-            let chunk = chunk.as_bytes();
-            let writer = self.writer.as_mut().expect("Brotli writer must exists");
-            if !chunk.is_empty() {
-                writer
-                    .write_all(&chunk)
-                    .expect("Failed to write to brotli encoder");
-                writer.flush().expect("Failed to flush brotli encoder");
-            }
-            if !msg.eom()? {
-                if !writer.get_ref().is_empty() {
-                    msg.set(writer.get_ref(), None, None)?;
-                    writer.get_mut().clear();
-                } else if !chunk.is_empty() {
-                    msg.remove(None, None)?;
-                }
-            } else {
-                let data = self.writer.take().unwrap().into_inner().unwrap();
-                msg.set(data, None, None)?;
-            }
-        }
-        Ok(None)
+impl BodyTransformer for BrotliEncoder {
+    fn transform_chunk(&mut self, chunk: &[u8]) -> LuaResult<Vec<u8>> {
+        let writer = self.0.as_mut().expect("Brotli writer must exists");
+        writer.write_all(chunk).into_lua_err()?;
+        writer.flush().into_lua_err()?;
+        Ok(std::mem::take(writer.get_mut()))
+    }
+
+    fn finish(&mut self) -> LuaResult<Vec<u8>> {
+        let writer = self.0.take().expect("Brotli writer must exists");
+        writer.into_inner().into_lua_err()
     }
 }
 
 #[mlua::lua_module(skip_memory_check)]
 fn haproxy_brotli_filter(lua: &Lua) -> LuaResult<bool> {
     let core = Core::new(lua)?;
-    core.register_filter::<BrotliFilter>("brotli")?;
+    core.register_body_filter::<BrotliFilter>("brotli")?;
     Ok(true)
 }
@@ -107,9 +107,9 @@ fn haproxy_prometheus_module(lua: &Lua) -> LuaResult<bool> {
             }
 
             // Tt and others are not available
-            let method = txn.f.get::<String>("method", ())?;
+            let method = txn.f.method()?;
             println!("method {method:?}");
-            let status = txn.f.get::<String>("status", ())?;
+            let status = txn.f.status()?;
             println!("status {status:?}");
 
             let start_time = txn
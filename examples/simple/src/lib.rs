@@ -1,4 +1,4 @@
-use haproxy_api::{Action, Core, ServiceMode, Txn};
+use haproxy_api::{Action, AppletHttp, Core, Txn};
 use mlua::prelude::*;
 
 #[mlua::lua_module(skip_memory_check)]
@@ -28,16 +28,15 @@ fn haproxy_simple_module(lua: &Lua) -> LuaResult<bool> {
         Ok(())
     })?;
 
-    let code = mlua::chunk! {
-        local applet = ...
-        local response = "Hello, World!"
-        applet:set_status(200)
-        applet:add_header("content-length", string.len(response))
-        applet:add_header("content-type", "text/plain")
-        applet:start_response()
-        applet:send(response)
-    };
-    core.register_lua_service("rust_service", ServiceMode::Http, code)?;
+    // Responds with a static greeting
+    core.register_service("rust_service", |_lua, applet: AppletHttp| async move {
+        let response = "Hello, World!";
+        applet.set_status(200, None)?;
+        applet.add_header("content-length", response.len())?;
+        applet.add_header("content-type", "text/plain")?;
+        applet.start_response()?;
+        applet.send(response).await
+    })?;
 
     Ok(true)
 }
@@ -0,0 +1,314 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::{env, fs};
+
+use mlua::{Result, String as LuaString};
+
+/// Typed arguments of an action registered with [`Core::register_action_with_args`].
+///
+/// Arguments are passed by HAProxy as strings after the action name
+/// (eg. `http-request lua.rate_limit 100 10s`).
+///
+/// Tuples of up to 6 types implementing [`FromStr`] can be used as arguments.
+///
+/// [`Core::register_action_with_args`]: crate::Core::register_action_with_args
+pub trait ActionArgs: Sized + 'static {
+    /// The number of arguments expected by the action (at most 6).
+    const NB_ARGS: usize;
+
+    /// Parses and validates the raw arguments.
+    fn parse(args: &[String]) -> Result<Self>;
+}
+
+impl ActionArgs for () {
+    const NB_ARGS: usize = 0;
+
+    fn parse(_: &[String]) -> Result<Self> {
+        Ok(())
+    }
+}
+
+// Parses the argument at the position `i` (starting at 0)
+fn parse_arg<T>(args: &[String], i: usize) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let arg = args
+        .get(i)
+        .ok_or_else(|| mlua::Error::runtime(format!("missing argument #{}", i + 1)))?;
+    arg.parse()
+        .map_err(|err| mlua::Error::runtime(format!("invalid argument #{} '{arg}': {err}", i + 1)))
+}
+
+macro_rules! impl_action_args {
+    ($nb:expr => $($name:ident $i:tt),+) => {
+        impl<$($name),+> ActionArgs for ($($name,)+)
+        where
+            $($name: FromStr + 'static, $name::Err: Display,)+
+        {
+            const NB_ARGS: usize = $nb;
+
+            fn parse(args: &[String]) -> Result<Self> {
+                Ok(($(parse_arg::<$name>(args, $i)?,)+))
+            }
+        }
+    };
+}
+
+impl_action_args!(1 => A 0);
+impl_action_args!(2 => A 0, B 1);
+impl_action_args!(3 => A 0, B 1, C 2);
+impl_action_args!(4 => A 0, B 1, C 2, D 3);
+impl_action_args!(5 => A 0, B 1, C 2, D 3, E 4);
+impl_action_args!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+
+// Maximum number of arguments of an action with typed arguments
+pub(crate) const MAX_ACTION_ARGS: usize = 6;
+
+// Raw arguments passed by HAProxy (unused trailing ones are `None`)
+pub(crate) type RawActionArgs = (
+    Option<LuaString>,
+    Option<LuaString>,
+    Option<LuaString>,
+    Option<LuaString>,
+    Option<LuaString>,
+    Option<LuaString>,
+);
+
+// Raw arguments of a directive with the parsing result
+type ParsedArgs<T> = (Vec<String>, Result<Rc<T>>);
+
+// Keeps parsed arguments of every directive using the action
+pub(crate) struct ActionArgsCache<T> {
+    name: String,
+    // Directives are few, so a linear search avoids allocating a key on every request
+    parsed: RefCell<Vec<ParsedArgs<T>>>,
+}
+
+impl<T: ActionArgs> ActionArgsCache<T> {
+    pub(crate) fn new(name: &str) -> Self {
+        ActionArgsCache {
+            name: name.to_string(),
+            parsed: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Parses arguments of the directives using the action in the configuration files loaded
+    // by HAProxy, returning an error which lists every invalid directive with its location
+    pub(crate) fn validate_config(&self) -> Result<()> {
+        let Ok(files) = env::var("HAPROXY_CFGFILES") else {
+            return Ok(());
+        };
+        let keyword = format!("lua.{}", self.name);
+        let mut errors = Vec::new();
+        for path in files.split(';').filter(|path| !path.is_empty()) {
+            // The configuration may come from a file which cannot be read again (eg. stdin)
+            let Ok(content) = fs::read_to_string(path) else {
+                continue;
+            };
+            for (line, args) in find_directives(&content, &keyword, T::NB_ARGS) {
+                if let Err(err) = self.parse(args, Some(&format!("{path}:{line}"))) {
+                    errors.push(error_message(err));
+                }
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(mlua::Error::runtime(errors.join("\n"))),
+        }
+    }
+
+    // Returns the parsed arguments, parsing them only on the first use
+    pub(crate) fn get(&self, args: RawActionArgs) -> Result<Rc<T>> {
+        let (a, b, c, d, e, f) = args;
+        let args = [a, b, c, d, e, f];
+        let args = &args[..T::NB_ARGS];
+        let matches = |parsed: &[String]| {
+            (parsed.iter().zip(args)).all(|(p, arg)| match arg {
+                Some(arg) => p.as_bytes() == &*arg.as_bytes(),
+                None => p.is_empty(),
+            })
+        };
+        if let Some((_, parsed)) = self.parsed.borrow().iter().find(|(p, _)| matches(p)) {
+            return parsed.clone();
+        }
+        let args = (args.iter())
+            .map(|arg| {
+                arg.as_ref()
+                    .map(|arg| arg.to_string_lossy())
+                    .unwrap_or_default()
+            })
+            .collect();
+        self.parse(args, None)
+    }
+
+    fn parse(&self, args: Vec<String>, location: Option<&str>) -> Result<Rc<T>> {
+        // The same arguments may be used by several directives
+        if let Some((_, parsed)) = self.parsed.borrow().iter().find(|(p, _)| *p == args) {
+            return parsed.clone();
+        }
+        let parsed = T::parse(&args).map(Rc::new).map_err(|err| {
+            let location = location.map(|loc| format!("{loc}: ")).unwrap_or_default();
+            mlua::Error::runtime(format!(
+                "{location}invalid arguments for action 'lua.{}' ({}): {}",
+                self.name,
+                args.join(" "),
+                error_message(err),
+            ))
+        });
+        (self.parsed.borrow_mut()).push((args, parsed.clone()));
+        parsed
+    }
+}
+
+// Returns the error message without the "runtime error" prefix
+fn error_message(err: mlua::Error) -> String {
+    match err {
+        mlua::Error::RuntimeError(msg) => msg,
+        err => err.to_string(),
+    }
+}
+
+// Finds rules using the action `keyword` in the configuration `content`.
+// Returns the line number and arguments of each of them.
+// Rules in conditional blocks or with unresolved environment variables are skipped.
+fn find_directives(content: &str, keyword: &str, nb_args: usize) -> Vec<(usize, Vec<String>)> {
+    let mut found = Vec::new();
+    let mut depth: usize = 0;
+    for (i, line) in content.lines().enumerate() {
+        let Some(words) = split_words(line) else {
+            continue;
+        };
+        // Position of the action in the rule
+        let pos = match words.iter().map(|word| word.as_str()).collect::<Vec<_>>()[..] {
+            [".if", ..] => {
+                depth += 1;
+                continue;
+            }
+            [".endif", ..] => {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            ["http-request" | "http-response" | "http-after-response", ..] => 1,
+            ["tcp-request", "content" | "connection" | "session", ..] => 2,
+            ["tcp-response", "content", ..] => 2,
+            _ => continue,
+        };
+        if depth > 0 || words.get(pos).map(|word| word.as_str()) != Some(keyword) {
+            continue;
+        }
+        if let Some(args) = words.get(pos + 1..pos + 1 + nb_args) {
+            found.push((i + 1, args.to_vec()));
+        }
+    }
+    found
+}
+
+// Splits the configuration line into words, handling quotes, escapes and comments
+// the way HAProxy does. Returns `None` if an environment variable cannot be resolved.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    // The current word, `None` between words (an empty quoted string is still a word)
+    let mut word: Option<String> = None;
+    let (mut squote, mut dquote) = (false, false);
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' if !dquote => {
+                squote = !squote;
+                word.get_or_insert_with(String::new);
+            }
+            '"' if !squote => {
+                dquote = !dquote;
+                word.get_or_insert_with(String::new);
+            }
+            _ if squote => word.get_or_insert_with(String::new).push(ch),
+            '\\' => {
+                if let Some(ch) = chars.next() {
+                    word.get_or_insert_with(String::new).push(ch);
+                }
+            }
+            '$' if dquote => {
+                let braces = chars.next_if_eq(&'{').is_some();
+                let mut name = String::new();
+                while let Some(ch) = chars.next_if(|ch| ch.is_ascii_alphanumeric() || *ch == '_') {
+                    name.push(ch);
+                }
+                if braces && chars.next_if_eq(&'}').is_none() {
+                    return None;
+                }
+                let value = env::var(&name).ok()?;
+                word.get_or_insert_with(String::new).push_str(&value);
+            }
+            '#' if !dquote => break,
+            _ if ch.is_ascii_whitespace() && !dquote => words.extend(word.take()),
+            _ => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        let words = split_words("  http-request lua.limit 100\t'10 s' # comment").unwrap();
+        assert_eq!(words, ["http-request", "lua.limit", "100", "10 s"]);
+
+        let words = split_words(r#"a "b c" '' x\ y "\"q\"" 'd"e' "f'g" h#i"#).unwrap();
+        assert_eq!(words, ["a", "b c", "", "x y", "\"q\"", "d\"e", "f'g", "h"]);
+
+        std::env::set_var("HAPROXY_API_TEST_VAR", "value");
+        let words = split_words(r#"a "${HAPROXY_API_TEST_VAR}/x" "$HAPROXY_API_TEST_VAR" '$b'"#);
+        assert_eq!(words.unwrap(), ["a", "value/x", "value", "$b"]);
+        assert_eq!(split_words(r#"a "${HAPROXY_API_TEST_UNSET}""#), None);
+    }
+
+    #[test]
+    fn test_find_directives() {
+        let config = r#"
+frontend fe
+    http-request lua.limit 100 10s if { path /api }
+    http-request set-header x-lua lua.limit
+    http-request set-header x-lua lua.limit a b
+    http-response set-var(txn.x) str(lua.limit) lua.limit 1 2
+    tcp-request inspect-delay lua.limit 1 2
+    tcp-request content lua.limit "200" 1m
+    http-request lua.limit 300
+    .if defined(LIMIT)
+        http-request lua.limit invalid 1s
+    .endif
+    # http-request lua.limit commented 1s
+    http-response lua.limit_other 1 2
+"#;
+        let found = find_directives(config, "lua.limit", 2);
+        assert_eq!(
+            found,
+            [
+                (3, vec!["100".to_string(), "10s".to_string()]),
+                (8, vec!["200".to_string(), "1m".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cache = ActionArgsCache::<(u32, String)>::new("limit");
+        let args = vec!["x".to_string(), "y".to_string()];
+        let err = cache.parse(args, Some("haproxy.cfg:3")).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "runtime error: haproxy.cfg:3: invalid arguments for action 'lua.limit' (x y): \
+             invalid argument #1 'x': invalid digit found in string"
+        );
+
+        let args = vec!["1".to_string(), "y".to_string()];
+        assert_eq!(*cache.parse(args, None).unwrap(), (1, "y".to_string()));
+    }
+}
@@ -0,0 +1,167 @@
+use std::ops::Deref;
+
+#[cfg(feature = "async")]
+use mlua::String as LuaString;
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value, WeakLua};
+
+#[cfg(feature = "async")]
+use crate::r#async::call_method_yielding;
+use crate::{Converters, Fetches, Headers};
+
+/// The "AppletHTTP" class is used with services registered in HTTP mode.
+///
+/// Functions that wait for data (`getline`, `receive`, `send`) yield in HAProxy, so they are
+/// asynchronous and can be used only in services registered with [`Core::register_service`].
+///
+/// [`Core::register_service`]: crate::Core::register_service
+#[derive(Clone)]
+pub struct AppletHttp {
+    class: Table,
+    pub c: Converters,
+    pub f: Fetches,
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    lua: WeakLua,
+}
+
+impl AppletHttp {
+    /// Returns the HTTP method of the request (eg: "GET").
+    #[inline]
+    pub fn method(&self) -> Result<String> {
+        self.class.get("method")
+    }
+
+    /// Returns the HTTP version of the request (eg: "1.1").
+    #[inline]
+    pub fn version(&self) -> Result<String> {
+        self.class.get("version")
+    }
+
+    /// Returns the path of the request.
+    #[inline]
+    pub fn path(&self) -> Result<String> {
+        self.class.get("path")
+    }
+
+    /// Returns the query string of the request (without the leading `?`).
+    #[inline]
+    pub fn qs(&self) -> Result<String> {
+        self.class.get("qs")
+    }
+
+    /// Returns the length of the request body.
+    #[inline]
+    pub fn length(&self) -> Result<usize> {
+        self.class.get("length")
+    }
+
+    /// Returns a `Headers` table containing all the request headers.
+    #[inline]
+    pub fn headers(&self) -> Result<Headers> {
+        self.class.get("headers")
+    }
+
+    /// Sets the HTTP status code for the response.
+    /// If no custom reason is provided, it will be generated from the status.
+    #[inline]
+    pub fn set_status(&self, status: u16, reason: Option<&str>) -> Result<()> {
+        self.class.call_method("set_status", (status, reason))
+    }
+
+    /// Appends an HTTP header field `name` with `value` in the response.
+    #[inline]
+    pub fn add_header(&self, name: &str, value: impl IntoLua) -> Result<()> {
+        self.class.call_method("add_header", (name, value))
+    }
+
+    /// Sends the response headers (status line and all added headers).
+    ///
+    /// Must be called before sending any data with [`AppletHttp::send`].
+    #[inline]
+    pub fn start_response(&self) -> Result<()> {
+        self.class.call_method("start_response", ())
+    }
+
+    /// Reads one line from the request body, including the trailing `\n`.
+    ///
+    /// Returns an empty string when the end of the body is reached.
+    #[cfg(feature = "async")]
+    pub async fn getline(&self) -> Result<LuaString> {
+        call_method_yielding(&self.lua.upgrade(), &self.class, "getline", ()).await
+    }
+
+    /// Reads `size` bytes from the request body, or the whole remaining body if `size` is `None`.
+    ///
+    /// Returns an empty string when the end of the body is reached.
+    #[cfg(feature = "async")]
+    pub async fn receive(&self, size: Option<usize>) -> Result<LuaString> {
+        call_method_yielding(&self.lua.upgrade(), &self.class, "receive", size).await
+    }
+
+    /// Sends the `data` as a part of the response body.
+    ///
+    /// Waits until the output buffer has room for the whole `data`.
+    #[cfg(feature = "async")]
+    pub async fn send(&self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = LuaString::wrap(data);
+        call_method_yielding(&self.lua.upgrade(), &self.class, "send", data).await
+    }
+
+    /// Returns data stored in the current applet (with the `set_priv()`) function.
+    #[inline]
+    pub fn get_priv<R: FromLua>(&self) -> Result<R> {
+        self.class.call_method("get_priv", ())
+    }
+
+    /// Stores any data in the current HAProxy applet.
+    /// This action replaces the old stored data.
+    #[inline]
+    pub fn set_priv(&self, val: impl IntoLua) -> Result<()> {
+        self.class.call_method("set_priv", val)
+    }
+
+    /// Returns data stored in the variable `name`.
+    #[inline]
+    pub fn get_var<R: FromLua>(&self, name: &str) -> Result<R> {
+        self.class.call_method("get_var", name)
+    }
+
+    /// Store variable `name` in an HAProxy converting the type.
+    #[inline]
+    pub fn set_var(&self, name: &str, val: impl IntoLua) -> Result<()> {
+        self.class.call_method("set_var", (name, val))
+    }
+
+    /// Store variable `name` in an HAProxy if the variable already exists.
+    #[inline]
+    pub fn set_var_if_exists(&self, name: &str, val: impl IntoLua) -> Result<()> {
+        self.class.call_method("set_var", (name, val, true))
+    }
+
+    /// Unsets the variable `name`.
+    #[inline]
+    pub fn unset_var(&self, name: &str) -> Result<()> {
+        self.class.call_method("unset_var", name)
+    }
+}
+
+impl FromLua for AppletHttp {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(AppletHttp {
+            c: class.get("c")?,
+            f: class.get("f")?,
+            class,
+            lua: lua.weak(),
+        })
+    }
+}
+
+impl Deref for AppletHttp {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.class
+    }
+}
@@ -0,0 +1,111 @@
+use std::ops::Deref;
+
+#[cfg(feature = "async")]
+use mlua::String as LuaString;
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value, WeakLua};
+
+#[cfg(feature = "async")]
+use crate::r#async::call_method_yielding;
+use crate::{Converters, Fetches};
+
+/// The "AppletTCP" class is used with services registered in TCP mode.
+///
+/// Functions that wait for data (`getline`, `receive`, `send`) yield in HAProxy, so they are
+/// asynchronous and can be used only in services registered with [`Core::register_tcp_service`].
+///
+/// [`Core::register_tcp_service`]: crate::Core::register_tcp_service
+#[derive(Clone)]
+pub struct AppletTcp {
+    class: Table,
+    pub c: Converters,
+    pub f: Fetches,
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    lua: WeakLua,
+}
+
+impl AppletTcp {
+    /// Reads one line from the input stream, including the trailing `\n`.
+    ///
+    /// Returns an empty string when the stream is closed.
+    #[cfg(feature = "async")]
+    pub async fn getline(&self) -> Result<LuaString> {
+        call_method_yielding(&self.lua.upgrade(), &self.class, "getline", ()).await
+    }
+
+    /// Reads `size` bytes from the input stream, or everything until the stream is closed
+    /// if `size` is `None`.
+    ///
+    /// Returns an empty string when the stream is closed.
+    #[cfg(feature = "async")]
+    pub async fn receive(&self, size: Option<usize>) -> Result<LuaString> {
+        call_method_yielding(&self.lua.upgrade(), &self.class, "receive", size).await
+    }
+
+    /// Sends the `data` to the output stream.
+    ///
+    /// Waits until the output buffer has room for the whole `data`.
+    #[cfg(feature = "async")]
+    pub async fn send(&self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = LuaString::wrap(data);
+        call_method_yielding(&self.lua.upgrade(), &self.class, "send", data).await
+    }
+
+    /// Returns data stored in the current applet (with the `set_priv()`) function.
+    #[inline]
+    pub fn get_priv<R: FromLua>(&self) -> Result<R> {
+        self.class.call_method("get_priv", ())
+    }
+
+    /// Stores any data in the current HAProxy applet.
+    /// This action replaces the old stored data.
+    #[inline]
+    pub fn set_priv(&self, val: impl IntoLua) -> Result<()> {
+        self.class.call_method("set_priv", val)
+    }
+
+    /// Returns data stored in the variable `name`.
+    #[inline]
+    pub fn get_var<R: FromLua>(&self, name: &str) -> Result<R> {
+        self.class.call_method("get_var", name)
+    }
+
+    /// Store variable `name` in an HAProxy converting the type.
+    #[inline]
+    pub fn set_var(&self, name: &str, val: impl IntoLua) -> Result<()> {
+        self.class.call_method("set_var", (name, val))
+    }
+
+    /// Store variable `name` in an HAProxy if the variable already exists.
+    #[inline]
+    pub fn set_var_if_exists(&self, name: &str, val: impl IntoLua) -> Result<()> {
+        self.class.call_method("set_var", (name, val, true))
+    }
+
+    /// Unsets the variable `name`.
+    #[inline]
+    pub fn unset_var(&self, name: &str) -> Result<()> {
+        self.class.call_method("unset_var", name)
+    }
+}
+
+impl FromLua for AppletTcp {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(AppletTcp {
+            c: class.get("c")?,
+            f: class.get("f")?,
+            class,
+            lua: lua.weak(),
+        })
+    }
+}
+
+impl Deref for AppletTcp {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.class
+    }
+}
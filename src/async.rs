@@ -8,8 +8,8 @@ use std::task::{Context, Poll};
 use dashmap::DashMap;
 use futures_util::future::Either;
 use mlua::{
-    ExternalResult, FromLuaMulti, Function, IntoLuaMulti, Lua, RegistryKey, Result, Table,
-    UserData, UserDataMethods, Value,
+    ExternalResult, FromLuaMulti, Function, IntoLuaMulti, Lua, MultiValue, RegistryKey, Result,
+    Table, UserData, UserDataMethods, Value,
 };
 use rustc_hash::FxBuildHasher;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -151,6 +151,58 @@ where
     })
 }
 
+/// Creates a new async function whose future runs in the HAProxy Lua coroutine.
+///
+/// Unlike [`create_async_function`], the future is not spawned on the tokio runtime but polled
+/// by HAProxy, so it does not need to be `Send` and has access to Lua.
+/// It can await HAProxy operations which yield (eg. [`Socket::receive`] or [`HttpClient::send`]).
+/// While the future is waiting for something else, HAProxy polls it every millisecond.
+///
+/// [`Socket::receive`]: crate::Socket::receive
+/// [`HttpClient::send`]: crate::HttpClient::send
+pub fn create_local_async_function<F, A, R, FR>(lua: &Lua, func: F) -> Result<Function>
+where
+    F: Fn(Lua, A) -> FR + 'static,
+    A: FromLuaMulti + 'static,
+    R: IntoLuaMulti + 'static,
+    FR: Future<Output = Result<R>> + 'static,
+{
+    let _yield_fixup = YieldFixUp::with_dispatcher(lua)?;
+    lua.create_async_function(func)
+}
+
+// Calls the Lua function `func` in the HAProxy Lua coroutine, allowing it to yield.
+// Must be awaited in a future created by `create_local_async_function`.
+pub(crate) async fn call_yielding<R: FromLuaMulti>(
+    lua: &Lua,
+    func: &Function,
+    args: impl IntoLuaMulti,
+) -> Result<R> {
+    let mut results = lua.yield_with::<MultiValue>((func, args)).await?;
+    match results.pop_front() {
+        Some(Value::Boolean(true)) => R::from_lua_multi(results, lua),
+        Some(Value::Boolean(false)) => Err(match results.pop_front() {
+            Some(Value::Error(err)) => *err,
+            Some(err) => mlua::Error::runtime(err.to_string()?),
+            None => mlua::Error::runtime("unknown error"),
+        }),
+        _ => Err(mlua::Error::runtime(
+            "yielding functions can be used only in local async functions",
+        )),
+    }
+}
+
+// Calls the method `name` of the HAProxy object in the HAProxy Lua coroutine, allowing it to yield.
+pub(crate) async fn call_method_yielding<R: FromLuaMulti>(
+    lua: &Lua,
+    object: &Table,
+    name: &str,
+    args: impl IntoLuaMulti,
+) -> Result<R> {
+    let method: Function = object.get(name)?;
+    call_yielding(lua, &method, (object, args)).await
+}
+
 struct YieldFixUp<'lua>(&'lua Lua, Function);
 
 impl<'lua> YieldFixUp<'lua> {
@@ -219,6 +271,39 @@ impl<'lua> YieldFixUp<'lua> {
         coroutine.set("yield", new_yield)?;
         Ok(YieldFixUp(lua, orig_yield))
     }
+
+    // Replaces `coroutine.yield` with a function executing calls requested by a local future
+    fn with_dispatcher(lua: &'lua Lua) -> Result<Self> {
+        let dispatcher =
+            match lua.named_registry_value::<Option<Function>>("__HAPROXY_DISPATCHER")? {
+                Some(dispatcher) => dispatcher,
+                None => {
+                    let dispatcher: Function = lua
+                        .load(
+                            r#"
+                        local msleep, pcall, type = core.msleep, pcall, type
+                        return function(func, ...)
+                            if type(func) ~= "function" then
+                                -- The future is not ready, give the hand back to HAProxy
+                                msleep(1)
+                                return
+                            end
+                            -- Running in the HAProxy coroutine, so the function can yield
+                            return pcall(func, ...)
+                        end
+                    "#,
+                        )
+                        .call(())?;
+                    lua.set_named_registry_value("__HAPROXY_DISPATCHER", &dispatcher)?;
+                    dispatcher
+                }
+            };
+
+        let coroutine: Table = lua.globals().get("coroutine")?;
+        let orig_yield: Function = coroutine.get("yield")?;
+        coroutine.set("yield", dispatcher)?;
+        Ok(YieldFixUp(lua, orig_yield))
+    }
 }
 
 impl<'lua> Drop for YieldFixUp<'lua> {
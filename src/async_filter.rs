@@ -0,0 +1,230 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use mlua::{ExternalResult, Lua, Result, Table};
+use tokio::task::JoinHandle;
+
+use crate::{
+    runtime, Channel, FilterFlags, FilterMethod, FilterResult, HttpMessage, Txn, UserFilter,
+};
+
+/// A future returned by [`AsyncUserFilter`] callbacks.
+pub type FilterFuture<T> = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+/// The channel or HTTP message an [`AsyncUserFilter`] callback was called on.
+#[derive(Clone)]
+pub enum FilterTarget {
+    Channel(Channel),
+    HttpMessage(HttpMessage),
+}
+
+impl FilterTarget {
+    fn is_resp(&self) -> Result<bool> {
+        match self {
+            FilterTarget::Channel(chn) => chn.is_resp(),
+            FilterTarget::HttpMessage(msg) => msg.is_resp(),
+        }
+    }
+}
+
+// Callbacks which can be executed asynchronously
+const ASYNC_METHODS: u8 = FilterMethod::START_ANALYZE
+    | FilterMethod::END_ANALYZE
+    | FilterMethod::HTTP_HEADERS
+    | FilterMethod::HTTP_END;
+
+/// A trait that defines asynchronous callback functions to implement filters.
+///
+/// Callbacks have access to the transaction and may return a future, which is spawned
+/// on the global tokio [`runtime`]. Filters cannot yield, so while the future is running
+/// the filter returns [`FilterResult::Wait`] and HAProxy calls it again every [`WAKE_TIME`]
+/// until the future completes. Then its output is passed to [`complete`], which decides
+/// the result returned to HAProxy.
+///
+/// Futures cannot access Lua, any changes to the transaction must be made in the callbacks
+/// or in [`complete`].
+/// Payload callbacks are not available for asynchronous filters.
+///
+/// [`WAKE_TIME`]: AsyncUserFilter::WAKE_TIME
+/// [`complete`]: AsyncUserFilter::complete
+pub trait AsyncUserFilter: Sized + 'static {
+    /// The value produced by the futures returned from the callbacks.
+    type Output: Send + 'static;
+
+    /// Sets methods available for this filter.
+    /// By default all non-payload methods
+    const METHODS: u8 = ASYNC_METHODS;
+
+    /// Sets configuration flags of this filter.
+    /// By default HTX (HTTP streams)
+    const FLAGS: u8 = FilterFlags::HTX;
+
+    /// Continue execution if a filter callback (or its future) returns an error.
+    const CONTINUE_IF_ERROR: bool = true;
+
+    /// How often to check whether a running future is completed.
+    const WAKE_TIME: Duration = Duration::from_millis(5);
+
+    /// Creates a new instance of filter.
+    fn new(lua: &Lua, args: Table) -> Result<Self>;
+
+    /// Called when the analysis starts on the channel `chn`.
+    fn start_analyze(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        chn: Channel,
+    ) -> Result<Option<FilterFuture<Self::Output>>> {
+        let _ = (lua, txn, chn);
+        Ok(None)
+    }
+
+    /// Called when the analysis ends on the channel `chn`.
+    fn end_analyze(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        chn: Channel,
+    ) -> Result<Option<FilterFuture<Self::Output>>> {
+        let _ = (lua, txn, chn);
+        Ok(None)
+    }
+
+    /// Called just before the HTTP payload analysis and after any processing on the HTTP message `msg`.
+    fn http_headers(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        msg: HttpMessage,
+    ) -> Result<Option<FilterFuture<Self::Output>>> {
+        let _ = (lua, txn, msg);
+        Ok(None)
+    }
+
+    /// Called after the HTTP payload analysis on the HTTP message `msg`.
+    fn http_end(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        msg: HttpMessage,
+    ) -> Result<Option<FilterFuture<Self::Output>>> {
+        let _ = (lua, txn, msg);
+        Ok(None)
+    }
+
+    /// Called when a future returned by the callback `method` (one of [`FilterMethod`] constants)
+    /// completes successfully, with the `target` of the callback and the future `output`.
+    ///
+    /// It runs on the HAProxy wake-up which observes the completion, so it has access to
+    /// the transaction. The returned result is passed to HAProxy as the callback result.
+    fn complete(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        method: u8,
+        target: FilterTarget,
+        output: Self::Output,
+    ) -> Result<FilterResult>;
+}
+
+// A running future with the callback method and direction (true for response)
+type PendingFuture<T> = (u8, bool, JoinHandle<Result<T>>);
+
+// Adapts `AsyncUserFilter` to `UserFilter`, keeping running futures between calls
+pub(crate) struct AsyncUserFilterWrapper<T: AsyncUserFilter> {
+    filter: T,
+    pending: Vec<PendingFuture<T::Output>>,
+}
+
+impl<T: AsyncUserFilter> AsyncUserFilterWrapper<T> {
+    fn poll(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        method: u8,
+        target: FilterTarget,
+        start: impl FnOnce(&mut T, Txn) -> Result<Option<FilterFuture<T::Output>>>,
+    ) -> Result<FilterResult> {
+        let is_resp = target.is_resp()?;
+        let pos = (self.pending.iter()).position(|(m, r, _)| *m == method && *r == is_resp);
+        let mut handle = match pos {
+            Some(pos) => self.pending.swap_remove(pos).2,
+            None => match start(&mut self.filter, txn.clone())? {
+                Some(fut) => runtime().spawn(fut),
+                None => return Ok(FilterResult::Continue),
+            },
+        };
+
+        if let Some(output) = (&mut handle).now_or_never() {
+            let output = output.into_lua_err()??;
+            return self.filter.complete(lua, txn, method, target, output);
+        }
+        self.pending.push((method, is_resp, handle));
+        Self::wake_time(lua, T::WAKE_TIME.as_millis() as u64)?;
+        Ok(FilterResult::Wait)
+    }
+}
+
+impl<T: AsyncUserFilter> UserFilter for AsyncUserFilterWrapper<T> {
+    const METHODS: u8 = T::METHODS & ASYNC_METHODS;
+    const FLAGS: u8 = T::FLAGS;
+    const CONTINUE_IF_ERROR: bool = T::CONTINUE_IF_ERROR;
+
+    fn new(lua: &Lua, args: Table) -> Result<Self> {
+        Ok(AsyncUserFilterWrapper {
+            filter: T::new(lua, args)?,
+            pending: Vec::new(),
+        })
+    }
+
+    fn start_analyze(&mut self, lua: &Lua, txn: Txn, chn: Channel) -> Result<FilterResult> {
+        let target = FilterTarget::Channel(chn.clone());
+        self.poll(
+            lua,
+            txn,
+            FilterMethod::START_ANALYZE,
+            target,
+            |filter, txn| filter.start_analyze(lua, txn, chn),
+        )
+    }
+
+    fn end_analyze(&mut self, lua: &Lua, txn: Txn, chn: Channel) -> Result<FilterResult> {
+        let target = FilterTarget::Channel(chn.clone());
+        self.poll(
+            lua,
+            txn,
+            FilterMethod::END_ANALYZE,
+            target,
+            |filter, txn| filter.end_analyze(lua, txn, chn),
+        )
+    }
+
+    fn http_headers(&mut self, lua: &Lua, txn: Txn, msg: HttpMessage) -> Result<FilterResult> {
+        let target = FilterTarget::HttpMessage(msg.clone());
+        self.poll(
+            lua,
+            txn,
+            FilterMethod::HTTP_HEADERS,
+            target,
+            |filter, txn| filter.http_headers(lua, txn, msg),
+        )
+    }
+
+    fn http_end(&mut self, lua: &Lua, txn: Txn, msg: HttpMessage) -> Result<FilterResult> {
+        let target = FilterTarget::HttpMessage(msg.clone());
+        self.poll(lua, txn, FilterMethod::HTTP_END, target, |filter, txn| {
+            filter.http_end(lua, txn, msg)
+        })
+    }
+}
+
+impl<T: AsyncUserFilter> Drop for AsyncUserFilterWrapper<T> {
+    fn drop(&mut self) {
+        // The stream is finished, nobody is waiting for the futures anymore
+        for (_, _, handle) in &self.pending {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use mlua::{BString, FromLua, IntoLuaMulti, Lua, MultiValue, Result, UserData, UserDataRef, Value};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::r#async::{create_async_function, runtime};
+use crate::AppletHttp;
+
+// Number of request body chunks buffered before the applet stops reading
+const BODY_CHANNEL_SIZE: usize = 4;
+
+// Number of response events buffered before the handler stops writing
+const EVENT_CHANNEL_SIZE: usize = 4;
+
+// Size of the request body chunks read from the applet
+const BODY_CHUNK_SIZE: usize = 16384;
+
+/// An HTTP request received by an asynchronous service.
+///
+/// See [`Core::register_async_service`](crate::Core::register_async_service) for more details.
+pub struct AsyncRequest {
+    pub method: String,
+    pub version: String,
+    pub path: String,
+    pub qs: String,
+    /// Request headers (names are lowercase).
+    ///
+    /// HAProxy exposes headers grouped by name, so different names come in arbitrary order,
+    /// only values of the same name keep the order they were received in.
+    pub headers: Vec<(String, BString)>,
+    pub body: RequestBody,
+}
+
+impl AsyncRequest {
+    /// Returns the first value of the header `name`.
+    pub fn header(&self, name: &str) -> Option<&BString> {
+        (self.headers.iter())
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+}
+
+/// A stream of request body chunks.
+pub struct RequestBody(mpsc::Receiver<BString>);
+
+impl RequestBody {
+    /// Receives the next chunk of the request body.
+    ///
+    /// Returns `None` when the whole body has been received.
+    pub async fn chunk(&mut self) -> Option<BString> {
+        self.0.recv().await
+    }
+}
+
+/// A sink used to write the response of an asynchronous service.
+///
+/// The status and headers are sent along with the first chunk of data,
+/// or when the handler returns successfully if no data was sent.
+/// If the handler fails before the response is started, a 500 response is sent instead.
+pub struct ResponseSink {
+    tx: mpsc::Sender<ResponseEvent>,
+    // Shared with the service to send the head once the handler is done
+    head: Arc<std::sync::Mutex<Option<ResponseHead>>>,
+}
+
+impl ResponseSink {
+    /// Sets the HTTP status code for the response.
+    /// If no custom reason is provided, it will be generated from the status.
+    ///
+    /// Has no effect once the response is started.
+    pub fn set_status(&mut self, status: u16, reason: Option<&str>) {
+        if let Some(head) = self.head.lock().unwrap().as_mut() {
+            head.status = status;
+            head.reason = reason.map(|r| r.to_string());
+        }
+    }
+
+    /// Appends an HTTP header field `name` with `value` in the response.
+    ///
+    /// Has no effect once the response is started.
+    pub fn add_header(&mut self, name: &str, value: impl AsRef<[u8]>) {
+        if let Some(head) = self.head.lock().unwrap().as_mut() {
+            (head.headers).push((name.to_string(), BString::from(value.as_ref())));
+        }
+    }
+
+    /// Sends the `data` as a part of the response body, starting the response if needed.
+    ///
+    /// Waits until the applet is ready to accept more data.
+    pub async fn send(&mut self, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.start_response().await?;
+        let data = BString::from(data.into());
+        (self.tx.send(ResponseEvent::Data(data)).await)
+            .map_err(|_| mlua::Error::runtime("applet is closed"))
+    }
+
+    /// Sends the status and headers, if not sent yet.
+    pub async fn start_response(&mut self) -> Result<()> {
+        let head = self.head.lock().unwrap().take();
+        if let Some(head) = head {
+            (self.tx.send(ResponseEvent::Head(head)).await)
+                .map_err(|_| mlua::Error::runtime("applet is closed"))?;
+        }
+        Ok(())
+    }
+}
+
+struct ResponseHead {
+    status: u16,
+    reason: Option<String>,
+    headers: Vec<(String, BString)>,
+}
+
+impl ResponseHead {
+    fn new(status: u16) -> Self {
+        ResponseHead {
+            status,
+            reason: None,
+            headers: Vec::new(),
+        }
+    }
+}
+
+enum ResponseEvent {
+    Head(ResponseHead),
+    Data(BString),
+    // The handler failed after the response was started
+    Error(String),
+    // The handler failed before the response was started, a 500 head was sent instead
+    Failed(String),
+}
+
+// Instruction for the Lua side of the service
+enum Step {
+    // Read next chunk of the request body
+    Input,
+    Event(ResponseEvent),
+    End,
+}
+
+impl IntoLuaMulti for Step {
+    fn into_lua_multi(self, lua: &Lua) -> Result<MultiValue> {
+        match self {
+            Step::Input => "input".into_lua_multi(lua),
+            Step::Event(ResponseEvent::Head(head)) => {
+                let headers = lua.create_table_with_capacity(head.headers.len(), 0)?;
+                for (name, value) in head.headers {
+                    let name = lua.create_string(name)?;
+                    let value = lua.create_string(value)?;
+                    headers.raw_push(lua.create_sequence_from([name, value])?)?;
+                }
+                ("head", head.status, head.reason, headers).into_lua_multi(lua)
+            }
+            Step::Event(ResponseEvent::Data(data)) => ("data", data).into_lua_multi(lua),
+            Step::Event(ResponseEvent::Error(err)) => ("error", err).into_lua_multi(lua),
+            Step::Event(ResponseEvent::Failed(err)) => ("failed", err).into_lua_multi(lua),
+            Step::End => "end".into_lua_multi(lua),
+        }
+    }
+}
+
+struct StreamState {
+    body_tx: Option<mpsc::Sender<BString>>,
+    body_eof: bool,
+    pending: Option<BString>,
+    events: mpsc::Receiver<ResponseEvent>,
+}
+
+#[derive(Clone)]
+struct ServiceStream(Arc<Mutex<StreamState>>);
+
+impl ServiceStream {
+    // Pushes request body to the handler and pulls response events from it.
+    // Returns as soon as the Lua side needs to act.
+    async fn step(self, chunk: Option<BString>, eof: bool) -> Step {
+        let mut state = self.0.lock().await;
+        let StreamState {
+            body_tx,
+            body_eof,
+            pending,
+            events,
+        } = &mut *state;
+        *body_eof |= eof;
+        if chunk.is_some() {
+            *pending = chunk;
+        }
+
+        loop {
+            match (body_tx.as_ref(), pending.take()) {
+                (Some(tx), Some(chunk)) => {
+                    // Do not block the response while the handler is not reading the body
+                    let closed = tokio::select! {
+                        biased;
+                        event = events.recv() => {
+                            *pending = Some(chunk);
+                            return event.map(Step::Event).unwrap_or(Step::End);
+                        }
+                        permit = tx.reserve() => match permit {
+                            Ok(permit) => {
+                                permit.send(chunk);
+                                false
+                            }
+                            Err(_) => true,
+                        }
+                    };
+                    if closed {
+                        *body_tx = None;
+                    }
+                }
+                (Some(tx), None) if !*body_eof && !tx.is_closed() => {
+                    return match events.try_recv() {
+                        Ok(event) => Step::Event(event),
+                        Err(_) => Step::Input,
+                    };
+                }
+                // The whole body was sent or the handler is not interested anymore
+                (Some(_), None) => *body_tx = None,
+                (None, _) => return events.recv().await.map(Step::Event).unwrap_or(Step::End),
+            }
+        }
+    }
+}
+
+impl UserData for ServiceStream {}
+
+impl FromLua for ServiceStream {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        Ok(UserDataRef::<Self>::from_lua(value, lua)?.clone())
+    }
+}
+
+pub(crate) fn create_async_service<F, FR>(lua: &Lua, func: F) -> Result<mlua::Function>
+where
+    F: Fn(AsyncRequest, ResponseSink) -> FR + 'static,
+    FR: Future<Output = Result<()>> + Send + 'static,
+{
+    let start = lua.create_function(move |_, applet: AppletHttp| {
+        let mut headers = Vec::new();
+        for kv in applet.headers()?.pairs::<BString>() {
+            let (name, values) = kv?;
+            headers.extend(values.into_iter().map(|v| (name.clone(), v)));
+        }
+
+        let (body_tx, body_rx) = mpsc::channel(BODY_CHANNEL_SIZE);
+        let (events_tx, events_rx) = mpsc::channel(EVENT_CHANNEL_SIZE);
+        let request = AsyncRequest {
+            method: applet.method()?,
+            version: applet.version()?,
+            path: applet.path()?,
+            qs: applet.qs()?,
+            headers,
+            body: RequestBody(body_rx),
+        };
+        let head = Arc::new(std::sync::Mutex::new(Some(ResponseHead::new(200))));
+        let sink = ResponseSink {
+            tx: events_tx.clone(),
+            head: head.clone(),
+        };
+
+        let _guard = runtime().enter();
+        let fut = func(request, sink);
+        runtime().spawn(async move {
+            let result = fut.await;
+            let head = head.lock().unwrap().take();
+            let events = match (result, head) {
+                (Ok(()), Some(head)) => vec![ResponseEvent::Head(head)],
+                (Ok(()), None) => vec![],
+                (Err(err), Some(_)) => vec![
+                    ResponseEvent::Head(ResponseHead::new(500)),
+                    ResponseEvent::Failed(err.to_string()),
+                ],
+                (Err(err), None) => vec![ResponseEvent::Error(err.to_string())],
+            };
+            for event in events {
+                let _ = events_tx.send(event).await;
+            }
+        });
+
+        Ok(ServiceStream(Arc::new(Mutex::new(StreamState {
+            body_tx: Some(body_tx),
+            body_eof: false,
+            pending: None,
+            events: events_rx,
+        }))))
+    })?;
+
+    let step = create_async_function(
+        lua,
+        |(stream, chunk, eof): (ServiceStream, Option<BString>, bool)| async move {
+            Ok(stream.step(chunk, eof).await)
+        },
+    )?;
+
+    lua.load(
+        r#"
+        local start, step, chunk_size = ...
+        return function(applet)
+            local stream = start(applet)
+            local chunk, eof = nil, false
+            while true do
+                local kind, a, b, c = step(stream, chunk, eof)
+                chunk = nil
+                if kind == "input" then
+                    chunk = applet:receive(chunk_size)
+                    if chunk == nil or #chunk == 0 then
+                        chunk, eof = nil, true
+                    end
+                elseif kind == "head" then
+                    applet:set_status(a, b)
+                    for _, h in ipairs(c) do
+                        applet:add_header(h[1], h[2])
+                    end
+                    applet:start_response()
+                elseif kind == "data" then
+                    applet:send(a)
+                elseif kind == "failed" then
+                    core.log(core.err, a)
+                elseif kind == "error" then
+                    error(a)
+                else
+                    return
+                end
+            end
+        end
+    "#,
+    )
+    .call((start, step, BODY_CHUNK_SIZE))
+}
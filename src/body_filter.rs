@@ -0,0 +1,135 @@
+use mlua::{Lua, Result, Table};
+
+use crate::{FilterMethod, FilterResult, HttpMessage, Txn, UserFilter};
+
+/// A streaming transformation of an HTTP message body.
+pub trait BodyTransformer: Sized + 'static {
+    /// Transforms the next `chunk` of the body and returns the data to forward.
+    ///
+    /// The returned data may be empty if the transformer needs more input.
+    fn transform_chunk(&mut self, chunk: &[u8]) -> Result<Vec<u8>>;
+
+    /// Called at the end of the body, returns the remaining data to forward.
+    fn finish(&mut self) -> Result<Vec<u8>>;
+}
+
+/// A filter that rewrites HTTP message bodies using a [`BodyTransformer`].
+///
+/// The filter decides on the message headers whether the body must be transformed,
+/// for both requests and responses. When a body is transformed, the message is switched
+/// to the chunked encoding (`Content-Length` is removed) and the `ETag` is made weak.
+///
+/// Transformed data replaces the input in the channel buffer. If it does not fit
+/// (eg. a transformer expanding the data a lot), the stream is aborted with an error
+/// rather than losing part of the body.
+///
+/// Register it using [`Core::register_body_filter`].
+///
+/// [`Core::register_body_filter`]: crate::Core::register_body_filter
+pub trait BodyFilter: Sized + 'static {
+    type Transformer: BodyTransformer;
+
+    /// Creates a new instance of filter.
+    fn new(lua: &Lua, args: Table) -> Result<Self>;
+
+    /// Called on the headers of the HTTP message `msg` (request or response).
+    ///
+    /// Returns a transformer to rewrite the message body, or `None` to keep it intact.
+    /// Headers describing the new body (eg. `Content-Encoding`) must be set here.
+    fn http_headers(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        msg: &HttpMessage,
+    ) -> Result<Option<Self::Transformer>>;
+}
+
+// Adapts `BodyFilter` to `UserFilter`, keeping transformers of each direction
+pub(crate) struct BodyFilterWrapper<T: BodyFilter> {
+    filter: T,
+    request: Option<T::Transformer>,
+    response: Option<T::Transformer>,
+}
+
+impl<T: BodyFilter> BodyFilterWrapper<T> {
+    // Replaces the incoming data of the message by the transformed `data`
+    fn replace_input(msg: &HttpMessage, data: Vec<u8>) -> Result<()> {
+        let len = data.len();
+        if msg.set(data, None, None)? < 0 {
+            return Err(mlua::Error::runtime(format!(
+                "not enough room in the buffer for {len} bytes of transformed body"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<T: BodyFilter> UserFilter for BodyFilterWrapper<T> {
+    const METHODS: u8 = FilterMethod::HTTP_HEADERS | FilterMethod::HTTP_PAYLOAD;
+
+    // Partially transformed body cannot be recovered
+    const CONTINUE_IF_ERROR: bool = false;
+
+    fn new(lua: &Lua, args: Table) -> Result<Self> {
+        Ok(BodyFilterWrapper {
+            filter: T::new(lua, args)?,
+            request: None,
+            response: None,
+        })
+    }
+
+    fn http_headers(&mut self, lua: &Lua, txn: Txn, msg: HttpMessage) -> Result<FilterResult> {
+        let Some(transformer) = self.filter.http_headers(lua, txn.clone(), &msg)? else {
+            return Ok(FilterResult::Continue);
+        };
+
+        // Strong validators are not valid for the transformed body anymore
+        let etags = msg.get_headers()?.get::<String>("etag")?;
+        if etags.iter().any(|etag| etag.starts_with('"')) {
+            msg.del_header("etag")?;
+            for etag in etags {
+                match etag.starts_with('"') {
+                    true => msg.add_header("etag", format!("W/{etag}"))?,
+                    false => msg.add_header("etag", etag)?,
+                }
+            }
+        }
+        // Switch to chunked transfer encoding
+        msg.set_body_len(None)?;
+
+        match msg.is_resp()? {
+            true => self.response = Some(transformer),
+            false => self.request = Some(transformer),
+        }
+        Self::register_data_filter(lua, txn, msg.channel()?)?;
+        Ok(FilterResult::Continue)
+    }
+
+    fn http_payload(&mut self, _: &Lua, _: Txn, msg: HttpMessage) -> Result<Option<usize>> {
+        let slot = match msg.is_resp()? {
+            true => &mut self.response,
+            false => &mut self.request,
+        };
+        let Some(transformer) = slot else {
+            return Ok(None);
+        };
+
+        let chunk = msg.body(None, Some(-1))?;
+        let chunk = chunk.as_ref().map(|chunk| chunk.as_bytes());
+        let chunk = chunk.as_deref().unwrap_or_default();
+        let mut data = match chunk.is_empty() {
+            true => Vec::new(),
+            false => transformer.transform_chunk(chunk)?,
+        };
+        if msg.eom()? {
+            data.extend(transformer.finish()?);
+            *slot = None;
+            Self::replace_input(&msg, data)?;
+        } else if !data.is_empty() {
+            Self::replace_input(&msg, data)?;
+        } else if !chunk.is_empty() {
+            msg.remove(None, None)?;
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+
+/// A writer used by CLI commands registered with [`Core::register_cli`] to produce their output.
+///
+/// The output is buffered and sent back to the HAProxy stats socket when the command returns.
+///
+/// [`Core::register_cli`]: crate::Core::register_cli
+#[derive(Debug, Default)]
+pub struct CliWriter(Vec<u8>);
+
+impl CliWriter {
+    /// Appends `line` followed by a newline to the output.
+    #[inline]
+    pub fn line(&mut self, line: impl AsRef<[u8]>) {
+        self.0.extend_from_slice(line.as_ref());
+        self.0.push(b'\n');
+    }
+
+    /// Appends raw `data` to the output.
+    #[inline]
+    pub fn write(&mut self, data: impl AsRef<[u8]>) {
+        self.0.extend_from_slice(data.as_ref());
+    }
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl fmt::Write for CliWriter {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
@@ -1,9 +1,29 @@
-use mlua::{FromLua, IntoLuaMulti, Lua, ObjectLike, Result, Table, Value};
+use std::net::IpAddr;
+
+use mlua::{
+    BString, FromLua, IntoLuaMulti, Lua, ObjectLike, Result, String as LuaString, Table, Value,
+};
 
 /// The "Converters" class allows to call a lot of internal HAProxy sample converters.
+///
+/// Common converters are available as typed methods, which return `None` if the conversion fails.
+/// Any other converter can be called using [`Converters::get`].
 #[derive(Clone)]
 pub struct Converters(Table);
 
+// Generates typed methods for converters without arguments
+macro_rules! converters {
+    ($($(#[$meta:meta])* $name:ident -> $ty:ty;)+) => {
+        $(
+            $(#[$meta])*
+            #[inline]
+            pub fn $name(&self, input: impl AsRef<[u8]>) -> Result<Option<$ty>> {
+                self.get(stringify!($name), LuaString::wrap(input))
+            }
+        )+
+    };
+}
+
 impl Converters {
     /// Executes an internal haproxy sample converter.
     #[inline]
@@ -19,6 +39,134 @@ impl Converters {
     pub fn get_str(&self, name: &str, args: impl IntoLuaMulti) -> Result<String> {
         Ok((self.0.call_method::<Option<_>>(name, args)?).unwrap_or_default())
     }
+
+    converters! {
+        /// Converts the `input` to lower case.
+        lower -> String;
+        /// Converts the `input` to upper case.
+        upper -> String;
+        /// Encodes the `input` in base64.
+        base64 -> String;
+        /// Decodes the base64 encoded `input`.
+        b64dec -> BString;
+        /// Encodes the `input` in base64url (URL and filename safe alphabet).
+        ub64enc -> String;
+        /// Decodes the base64url encoded `input`.
+        ub64dec -> BString;
+        /// Converts the `input` to a string of upper case hexadecimal digits.
+        hex -> String;
+        /// Converts the string of hexadecimal digits `input` to binary.
+        hex2bin -> BString;
+        /// Decodes the URL encoded `input`.
+        url_dec -> String;
+        /// Computes the SHA-1 digest of the `input`.
+        sha1 -> BString;
+        /// Computes the CRC32 hash of the `input`.
+        crc32 -> u32;
+        /// Computes the DJB2 hash of the `input`.
+        djb2 -> u32;
+        /// Returns the length of the string `input`.
+        length -> usize;
+    }
+
+    /// Computes the SHA-2 digest of the `input`.
+    ///
+    /// The `bits` can be 224, 256 (by default), 384 or 512.
+    #[inline]
+    pub fn sha2(&self, input: impl AsRef<[u8]>, bits: Option<u16>) -> Result<Option<BString>> {
+        let input = LuaString::wrap(input);
+        match bits {
+            Some(bits) => self.get("sha2", (input, bits)),
+            None => self.get("sha2", input),
+        }
+    }
+
+    /// Escapes the `input` to be used in a JSON string.
+    ///
+    /// The optional `input_code` defines how invalid UTF-8 is handled
+    /// ("ascii", "utf8", "utf8s", "utf8p" or "utf8ps").
+    #[inline]
+    pub fn json(
+        &self,
+        input: impl AsRef<[u8]>,
+        input_code: Option<&str>,
+    ) -> Result<Option<String>> {
+        let input = LuaString::wrap(input);
+        match input_code {
+            Some(input_code) => self.get("json", (input, input_code)),
+            None => self.get("json", input),
+        }
+    }
+
+    /// Applies the network masks to the `input` address.
+    ///
+    /// The `mask4` (in bits) is applied to IPv4 and the optional `mask6` to IPv6 addresses.
+    #[inline]
+    pub fn ipmask(&self, input: IpAddr, mask4: u8, mask6: Option<u8>) -> Result<Option<IpAddr>> {
+        let input = input.to_string();
+        let addr: Option<String> = match mask6 {
+            Some(mask6) => self.get("ipmask", (input, mask4, mask6))?,
+            None => self.get("ipmask", (input, mask4))?,
+        };
+        match addr {
+            Some(addr) => (addr.parse().map(Some))
+                .map_err(|err| mlua::Error::runtime(format!("invalid address '{addr}': {err}"))),
+            None => Ok(None),
+        }
+    }
+
+    /// Extracts the substring at the `index` (starting at 1, negative values count from the end)
+    /// considering the `input` as a list of fields separated by any of the `delimiters`.
+    ///
+    /// The optional `count` allows to extract multiple consecutive fields.
+    #[inline]
+    pub fn field(
+        &self,
+        input: &str,
+        index: i32,
+        delimiters: &str,
+        count: Option<i32>,
+    ) -> Result<Option<String>> {
+        match count {
+            Some(count) => self.get("field", (input, index, delimiters, count)),
+            None => self.get("field", (input, index, delimiters)),
+        }
+    }
+
+    /// Extracts the nth word at the `index` (starting at 1, negative values count from the end)
+    /// considering the `input` as a list of words separated by any of the `delimiters`.
+    ///
+    /// Unlike [`Converters::field`], consecutive delimiters are considered as one.
+    #[inline]
+    pub fn word(
+        &self,
+        input: &str,
+        index: i32,
+        delimiters: &str,
+        count: Option<i32>,
+    ) -> Result<Option<String>> {
+        match count {
+            Some(count) => self.get("word", (input, index, delimiters, count)),
+            None => self.get("word", (input, index, delimiters)),
+        }
+    }
+
+    /// Replaces the matches of the `regex` in the `input` by the `subst`.
+    ///
+    /// The optional `flags` can contain "i" (case insensitive) and "g" (replace all matches).
+    #[inline]
+    pub fn regsub(
+        &self,
+        input: &str,
+        regex: &str,
+        subst: &str,
+        flags: Option<&str>,
+    ) -> Result<Option<String>> {
+        match flags {
+            Some(flags) => self.get("regsub", (input, regex, subst, flags)),
+            None => self.get("regsub", (input, regex, subst)),
+        }
+    }
 }
 
 impl FromLua for Converters {
@@ -2,13 +2,25 @@ use std::collections::HashMap;
 #[cfg(feature = "async")]
 use std::future::Future;
 use std::ops::Deref;
+use std::rc::Rc;
+use std::time::Duration;
 
 use mlua::{
-    AnyUserData, AsChunk, Chunk, FromLuaMulti, IntoLua, Lua, ObjectLike, Result, Table, Value,
+    AnyUserData, AsChunk, Chunk, FromLua, FromLuaMulti, Function, IntoLua, Lua, ObjectLike, Result,
+    String as LuaString, Table, Value,
 };
 
+use crate::action_args::{ActionArgsCache, RawActionArgs, MAX_ACTION_ARGS};
+#[cfg(feature = "async")]
+use crate::async_filter::AsyncUserFilterWrapper;
+use crate::body_filter::BodyFilterWrapper;
 use crate::filter::UserFilterWrapper;
-use crate::{EventSub, Proxy, UserFilter};
+use crate::{
+    ActionArgs, BodyFilter, CliWriter, EventSub, HttpClient, Patref, Proxy, Queue, Socket, Txn,
+    UserFilter,
+};
+#[cfg(feature = "async")]
+use crate::{AppletHttp, AppletTcp, AsyncRequest, AsyncUserFilter, ResponseSink};
 
 /// The "Core" class contains all the HAProxy core functions.
 ///
@@ -46,6 +58,49 @@ impl Action {
     }
 }
 
+/// The result of an action returned to HAProxy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ActionResult {
+    /// Continues the processing with the next rule.
+    Continue,
+    /// Stops the evaluation of the current ruleset.
+    Stop,
+    /// Pauses the action, it is called again on the next event or when `wake_time` expires.
+    Yield(Option<Duration>),
+    /// Stops the processing with an internal error.
+    Error,
+    /// Stops the processing of the current stream, the response is already sent.
+    Done,
+    /// Denies the request (HTTP 403 or connection close for TCP rules).
+    Deny,
+    /// Aborts the processing, the client closed the connection.
+    Abort,
+    /// Stops the processing with an invalid request error.
+    Invalid,
+}
+
+impl ActionResult {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ActionResult::Continue => "CONTINUE",
+            ActionResult::Stop => "STOP",
+            ActionResult::Yield(_) => "YIELD",
+            ActionResult::Error => "ERROR",
+            ActionResult::Done => "DONE",
+            ActionResult::Deny => "DENY",
+            ActionResult::Abort => "ABORT",
+            ActionResult::Invalid => "INVALID",
+        }
+    }
+}
+
+impl From<()> for ActionResult {
+    #[inline]
+    fn from(_: ()) -> Self {
+        ActionResult::Continue
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum ServiceMode {
     Tcp,
@@ -130,6 +185,39 @@ impl<'lua> Core<'lua> {
         self.class.call_function("set_map", (filename, key, value))
     }
 
+    /// Returns data stored in the process-wide variable `name` (eg. "proc.threshold").
+    ///
+    /// Only variables of the `proc` scope are available outside of a transaction.
+    #[inline]
+    pub fn get_var<R: FromLua>(&self, name: &str) -> Result<R> {
+        self.class.call_function("get_var", name)
+    }
+
+    /// Stores the process-wide variable `name` in an HAProxy converting the type.
+    #[inline]
+    pub fn set_var(&self, name: &str, val: impl IntoLua) -> Result<()> {
+        self.class.call_function("set_var", (name, val))
+    }
+
+    /// Stores the process-wide variable `name` in an HAProxy if the variable already exists.
+    #[inline]
+    pub fn set_var_if_exists(&self, name: &str, val: impl IntoLua) -> Result<()> {
+        self.class.call_function("set_var", (name, val, true))
+    }
+
+    /// Unsets the process-wide variable `name`.
+    #[inline]
+    pub fn unset_var(&self, name: &str) -> Result<()> {
+        self.class.call_function("unset_var", name)
+    }
+
+    /// Returns the pattern reference `name` (eg. a map or ACL file name) for atomic updates.
+    /// This is HAProxy >=3.1 feature.
+    #[inline]
+    pub fn get_patref(&self, name: &str) -> Result<Patref> {
+        self.class.call_function("get_patref", name)
+    }
+
     /// Returns HAProxy core information (uptime, pid, memory pool usage, tasks number, ...).
     #[inline]
     pub fn get_info(&self) -> Result<Table> {
@@ -151,7 +239,10 @@ impl<'lua> Core<'lua> {
     /// Registers a function executed as an action.
     /// The expected actions are `tcp-req`, `tcp-res`, `http-req`, `http-res` or `http-after-res`.
     /// All the registered actions can be used in HAProxy with the prefix `lua.`.
-    pub fn register_action<F, A>(
+    ///
+    /// The function can return `()` to continue the processing or an [`ActionResult`]
+    /// to stop it, deny the request or pause the action.
+    pub fn register_action<F, A, R>(
         &self,
         name: &str,
         actions: &[Action],
@@ -159,19 +250,70 @@ impl<'lua> Core<'lua> {
         func: F,
     ) -> Result<()>
     where
-        F: Fn(&Lua, A) -> Result<()> + Send + 'static,
+        F: Fn(&Lua, A) -> Result<R> + Send + 'static,
         A: FromLuaMulti,
+        R: Into<ActionResult>,
     {
-        let func = self.lua.create_function(func)?;
+        let func = self
+            .lua
+            .create_function(move |lua, args| func(lua, args).map(R::into))?;
         let actions = actions.iter().map(|act| act.as_str()).collect::<Vec<_>>();
         self.class
             .call_function("register_action", (name, actions, func, nb_args))
     }
 
+    /// Registers a function executed as an action with typed arguments.
+    ///
+    /// Arguments of each directive using the action are parsed (see [`ActionArgs`]) once
+    /// and reused for the next requests.
+    ///
+    /// HAProxy does not pass the arguments to Lua while parsing the configuration, so when it
+    /// finishes initialization the directives are looked up in the loaded configuration files
+    /// (listed in the `HAPROXY_CFGFILES` environment variable). Invalid ones prevent HAProxy
+    /// from starting, with errors pointing at their file and line.
+    ///
+    /// Directives which cannot be found this way (eg. inside conditional blocks) are parsed on
+    /// their first execution. If invalid, they fail on every execution with an error naming
+    /// the action and its arguments.
+    pub fn register_action_with_args<F, T, R>(
+        &self,
+        name: &str,
+        actions: &[Action],
+        func: F,
+    ) -> Result<()>
+    where
+        F: Fn(&Lua, Txn, &T) -> Result<R> + Send + 'static,
+        T: ActionArgs,
+        R: Into<ActionResult>,
+    {
+        if T::NB_ARGS > MAX_ACTION_ARGS {
+            return Err(mlua::Error::runtime(format!(
+                "action 'lua.{name}' cannot have more than {MAX_ACTION_ARGS} arguments"
+            )));
+        }
+
+        let args_cache = Rc::new(ActionArgsCache::<T>::new(name));
+        let validate = {
+            let args_cache = args_cache.clone();
+            (self.lua).create_function(move |_, ()| args_cache.validate_config())?
+        };
+        self.class.call_function::<()>("register_init", validate)?;
+
+        let func = self
+            .lua
+            .create_function(move |lua, (txn, args): (Txn, RawActionArgs)| {
+                let args = args_cache.get(args)?;
+                func(lua, txn, &args).map(R::into)
+            })?;
+        let actions = actions.iter().map(|act| act.as_str()).collect::<Vec<_>>();
+        self.class
+            .call_function("register_action", (name, actions, func, T::NB_ARGS))
+    }
+
     /// Registers an asynchronous function executed as an action.
     ///
     /// See [`Core::register_action`] for more details.
-    pub fn register_async_action<F, A, FR>(
+    pub fn register_async_action<F, A, FR, R>(
         &self,
         name: &str,
         actions: &[Action],
@@ -181,9 +323,43 @@ impl<'lua> Core<'lua> {
     where
         F: Fn(A) -> FR + 'static,
         A: FromLuaMulti + 'static,
-        FR: Future<Output = Result<()>> + Send + 'static,
+        FR: Future<Output = Result<R>> + Send + 'static,
+        R: Into<ActionResult> + Send + 'static,
+    {
+        let func = crate::r#async::create_async_function(self.lua, move |args| {
+            let fut = func(args);
+            async move { fut.await.map(R::into) }
+        })?;
+        let actions = actions.iter().map(|act| act.as_str()).collect::<Vec<_>>();
+        self.class
+            .call_function("register_action", (name, actions, func, nb_args))
+    }
+
+    /// Registers an asynchronous function executed as an action in the HAProxy Lua coroutine.
+    ///
+    /// Unlike [`Core::register_async_action`], the future is polled by HAProxy
+    /// (see [`create_local_async_function`]), so it has access to the transaction and can wait
+    /// for HAProxy operations which yield, like [`Socket`] or [`HttpClient`] I/O.
+    ///
+    /// [`create_local_async_function`]: crate::create_local_async_function
+    #[cfg(feature = "async")]
+    pub fn register_local_async_action<F, A, FR, R>(
+        &self,
+        name: &str,
+        actions: &[Action],
+        nb_args: usize,
+        func: F,
+    ) -> Result<()>
+    where
+        F: Fn(Lua, A) -> FR + 'static,
+        A: FromLuaMulti + 'static,
+        FR: Future<Output = Result<R>> + 'static,
+        R: Into<ActionResult> + 'static,
     {
-        let func = crate::r#async::create_async_function(self.lua, func)?;
+        let func = crate::r#async::create_local_async_function(self.lua, move |lua, args| {
+            let fut = func(lua, args);
+            async move { fut.await.map(R::into) }
+        })?;
         let actions = actions.iter().map(|act| act.as_str()).collect::<Vec<_>>();
         self.class
             .call_function("register_action", (name, actions, func, nb_args))
@@ -258,6 +434,69 @@ impl<'lua> Core<'lua> {
             .call_function("register_filter", (name, filter_class, func))
     }
 
+    /// Registers a custom filter that implements [`BodyFilter`] trait.
+    pub fn register_body_filter<T: BodyFilter>(&self, name: &str) -> Result<()> {
+        self.register_filter::<BodyFilterWrapper<T>>(name)
+    }
+
+    /// Registers a custom filter that implements [`AsyncUserFilter`] trait.
+    #[cfg(feature = "async")]
+    pub fn register_async_filter<T: AsyncUserFilter>(&self, name: &str) -> Result<()> {
+        self.register_filter::<AsyncUserFilterWrapper<T>>(name)
+    }
+
+    /// Registers an asynchronous function executed as a service in HTTP mode.
+    /// All the registered service can be used in HAProxy with the prefix `lua.`.
+    ///
+    /// The future runs in the HAProxy Lua coroutine (see [`create_local_async_function`]),
+    /// so it can read the request body and send the response using the [`AppletHttp`] methods.
+    ///
+    /// [`create_local_async_function`]: crate::create_local_async_function
+    #[cfg(feature = "async")]
+    pub fn register_service<F, FR>(&self, name: &str, func: F) -> Result<()>
+    where
+        F: Fn(Lua, AppletHttp) -> FR + 'static,
+        FR: Future<Output = Result<()>> + 'static,
+    {
+        let func = crate::r#async::create_local_async_function(self.lua, func)?;
+        self.class
+            .call_function("register_service", (name, "http", func))
+    }
+
+    /// Registers an asynchronous function executed as a service in TCP mode.
+    /// All the registered service can be used in HAProxy with the prefix `lua.`.
+    ///
+    /// The future runs in the HAProxy Lua coroutine (see [`create_local_async_function`]),
+    /// so it can wait for data using the [`AppletTcp`] methods.
+    ///
+    /// [`create_local_async_function`]: crate::create_local_async_function
+    #[cfg(feature = "async")]
+    pub fn register_tcp_service<F, FR>(&self, name: &str, func: F) -> Result<()>
+    where
+        F: Fn(Lua, AppletTcp) -> FR + 'static,
+        FR: Future<Output = Result<()>> + 'static,
+    {
+        let func = crate::r#async::create_local_async_function(self.lua, func)?;
+        self.class
+            .call_function("register_service", (name, "tcp", func))
+    }
+
+    /// Registers an asynchronous function executed as a service in HTTP mode.
+    ///
+    /// The function receives the request with a stream of body chunks and a sink to write the response.
+    /// Chunks are passed between the Tokio runtime and HAProxy as they arrive, so the request and
+    /// response bodies are never fully buffered in memory.
+    #[cfg(feature = "async")]
+    pub fn register_async_service<F, FR>(&self, name: &str, func: F) -> Result<()>
+    where
+        F: Fn(AsyncRequest, ResponseSink) -> FR + 'static,
+        FR: Future<Output = Result<()>> + Send + 'static,
+    {
+        let func = crate::async_service::create_async_service(self.lua, func)?;
+        self.class
+            .call_function("register_service", (name, "http", func))
+    }
+
     /// Registers a Lua function executed as a service.
     /// All the registered service can be used in HAProxy with the prefix `lua.`.
     pub fn register_lua_service(
@@ -306,6 +545,19 @@ impl<'lua> Core<'lua> {
         self.class.call_function("register_task", func)
     }
 
+    /// Registers and start an independent asynchronous task running in the HAProxy Lua coroutine.
+    ///
+    /// See [`Core::register_local_async_action`] for the difference with [`Core::register_async_task`].
+    #[cfg(feature = "async")]
+    pub fn register_local_async_task<F, FR>(&self, func: F) -> Result<()>
+    where
+        F: Fn(Lua) -> FR + 'static,
+        FR: Future<Output = Result<()>> + 'static,
+    {
+        let func = crate::r#async::create_local_async_function(self.lua, move |lua, ()| func(lua))?;
+        self.class.call_function("register_task", func)
+    }
+
     /// Same as [`register_task`] but using Lua function.
     ///
     /// [`register_task`]: #method.register_task
@@ -314,6 +566,38 @@ impl<'lua> Core<'lua> {
         self.class.call_function("register_task", func)
     }
 
+    /// Registers a function executed as a cli command.
+    ///
+    /// The function receives all the words of the command (including the `path`)
+    /// and a [`CliWriter`] to produce the output sent back to the HAProxy stats socket.
+    pub fn register_cli<F>(&self, path: &[&str], usage: &str, func: F) -> Result<()>
+    where
+        F: Fn(&Lua, Vec<String>, &mut CliWriter) -> Result<()> + Send + 'static,
+    {
+        let func = self.lua.create_function(move |lua, args: Vec<String>| {
+            let mut out = CliWriter::default();
+            func(lua, args, &mut out)?;
+            Ok(LuaString::wrap(out.into_inner()))
+        })?;
+        // Output is sent from Lua as the applet may need to yield
+        let func: Function = self
+            .lua
+            .load(
+                r#"
+                local func = ...
+                return function(applet, ...)
+                    local out = func({...})
+                    if #out > 0 then
+                        applet:send(out)
+                    end
+                end
+            "#,
+            )
+            .call(func)?;
+        self.class
+            .call_function("register_cli", (path, usage, func))
+    }
+
     /// Registers a Lua function executed as a cli command.
     pub fn register_lua_cli(&self, path: &[&str], usage: &str, code: impl AsChunk) -> Result<()> {
         let func = self.lua.load(code).into_function()?;
@@ -321,6 +605,25 @@ impl<'lua> Core<'lua> {
             .call_function("register_cli", (path, usage, func))
     }
 
+    /// Creates a new non-blocking TCP [`Socket`].
+    #[inline]
+    pub fn tcp(&self) -> Result<Socket> {
+        self.class.call_function("tcp", ())
+    }
+
+    /// Creates a new [`HttpClient`] object.
+    /// This is HAProxy >=2.5 feature.
+    #[inline]
+    pub fn httpclient(&self) -> Result<HttpClient> {
+        self.class.call_function("httpclient", ())
+    }
+
+    /// Creates a new [`Queue`] object.
+    #[inline]
+    pub fn queue<T>(&self) -> Result<Queue<T>> {
+        self.class.call_function("queue", ())
+    }
+
     /// Changes the nice of the current task or current session.
     #[inline]
     pub fn set_nice(&self, nice: i32) -> Result<()> {
@@ -355,6 +658,16 @@ impl Deref for Core<'_> {
     }
 }
 
+impl IntoLua for ActionResult {
+    fn into_lua(self, lua: &Lua) -> Result<Value> {
+        let act: Table = lua.globals().get("act")?;
+        if let ActionResult::Yield(Some(wake_time)) = self {
+            act.call_function::<()>("wake_time", wake_time.as_millis() as u64)?;
+        }
+        act.get(self.as_str())
+    }
+}
+
 impl IntoLua for LogLevel {
     #[inline]
     fn into_lua(self, lua: &Lua) -> Result<Value> {
@@ -0,0 +1,107 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use mlua::{AnyUserData, Lua, Result, Table, UserDataRefMut};
+
+// Registry key of the table which holds extensions of every running stream
+const EXTENSIONS_KEY: &str = "__HAPROXY_TXN_EXTENSIONS";
+
+/// A type map of Rust values attached to the current transaction.
+///
+/// Values are stored in a Lua userdata without any conversion, so they can be of any type.
+/// Only one value per type is stored, use newtypes to keep several values of the same type.
+///
+/// HAProxy runs actions, sample fetches and converters of a stream in the same Lua coroutine,
+/// and extensions are bound to it. They are not dropped when the stream ends but when the
+/// Lua garbage collector collects the coroutine, which may happen much later.
+///
+/// HAProxy replaces the coroutine after any Lua error raised while processing the stream,
+/// including the one raised by [`Txn::done`]. Extensions set before are not visible anymore
+/// to the following actions of the same stream, so they should be treated as a cache.
+/// Filters have their own Lua context, extensions set there are not visible to actions.
+///
+/// [`Txn::done`]: crate::Txn::done
+#[derive(Clone, Debug)]
+pub struct Extensions(AnyUserData);
+
+#[derive(Default)]
+struct ExtensionsMap(HashMap<TypeId, Box<dyn Any>>);
+
+impl Extensions {
+    // Returns extensions bound to the currently running Lua coroutine (creating them if needed)
+    pub(crate) fn current(lua: &Lua) -> Result<Self> {
+        let streams = match lua.named_registry_value::<Option<Table>>(EXTENSIONS_KEY)? {
+            Some(streams) => streams,
+            None => {
+                // Weak keys allow to release extensions when the coroutine is collected
+                let streams = lua.create_table()?;
+                let mt = lua.create_table_from([("__mode", "k")])?;
+                streams.set_metatable(Some(mt))?;
+                lua.set_named_registry_value(EXTENSIONS_KEY, &streams)?;
+                streams
+            }
+        };
+
+        let thread = lua.current_thread();
+        if let Some(ud) = streams.raw_get::<Option<AnyUserData>>(&thread)? {
+            return Ok(Extensions(ud));
+        }
+        let ud = lua.create_any_userdata(ExtensionsMap::default())?;
+        streams.raw_set(thread, &ud)?;
+        Ok(Extensions(ud))
+    }
+
+    /// Inserts the `val` into the extensions.
+    ///
+    /// Returns the previously stored value of the same type.
+    pub fn insert<T: 'static>(&self, val: T) -> Result<Option<T>> {
+        let prev = self.map()?.0.insert(TypeId::of::<T>(), Box::new(val));
+        Ok(prev.and_then(|prev| prev.downcast().ok().map(|prev| *prev)))
+    }
+
+    /// Returns a clone of the stored value of type `T`.
+    pub fn get<T: Clone + 'static>(&self) -> Result<Option<T>> {
+        self.with(|val: &T| val.clone())
+    }
+
+    /// Calls the function `f` with a reference to the stored value of type `T`.
+    ///
+    /// Returns `None` if there is no value of this type.
+    /// Extensions are locked while `f` is running and cannot be accessed from it.
+    pub fn with<T: 'static, R>(&self, f: impl FnOnce(&T) -> R) -> Result<Option<R>> {
+        let map = self.map()?;
+        let val = map.0.get(&TypeId::of::<T>());
+        Ok(val.and_then(|val| val.downcast_ref()).map(f))
+    }
+
+    /// Calls the function `f` with a mutable reference to the stored value of type `T`.
+    ///
+    /// Returns `None` if there is no value of this type.
+    pub fn with_mut<T: 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<Option<R>> {
+        let mut map = self.map()?;
+        let val = map.0.get_mut(&TypeId::of::<T>());
+        Ok(val.and_then(|val| val.downcast_mut()).map(f))
+    }
+
+    /// Returns true if a value of type `T` is stored.
+    pub fn contains<T: 'static>(&self) -> Result<bool> {
+        Ok(self.map()?.0.contains_key(&TypeId::of::<T>()))
+    }
+
+    /// Removes and returns the stored value of type `T`.
+    pub fn remove<T: 'static>(&self) -> Result<Option<T>> {
+        let val = self.map()?.0.remove(&TypeId::of::<T>());
+        Ok(val.and_then(|val| val.downcast().ok().map(|val| *val)))
+    }
+
+    /// Removes all stored values.
+    pub fn clear(&self) -> Result<()> {
+        self.map()?.0.clear();
+        Ok(())
+    }
+
+    #[inline]
+    fn map(&self) -> Result<UserDataRefMut<ExtensionsMap>> {
+        self.0.borrow_mut::<ExtensionsMap>()
+    }
+}
@@ -1,9 +1,42 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
 use mlua::{FromLua, IntoLuaMulti, Lua, ObjectLike, Result, Table, Value};
 
 /// The "Fetches" class allows to call a lot of internal HAProxy sample fetches.
+///
+/// Common sample fetches are available as typed methods, which return `None` if the sample
+/// is not available in the current context. Any other sample fetch can be called using [`Fetches::get`].
 #[derive(Clone)]
 pub struct Fetches(Table);
 
+// Generates typed methods for sample fetches without arguments
+macro_rules! fetches {
+    ($($(#[$meta:meta])* $name:ident -> $ty:ty;)+) => {
+        $(
+            $(#[$meta])*
+            #[inline]
+            pub fn $name(&self) -> Result<Option<$ty>> {
+                self.get(stringify!($name), ())
+            }
+        )+
+    };
+}
+
+// Generates typed methods for sample fetches returning time in milliseconds
+macro_rules! timer_fetches {
+    ($($(#[$meta:meta])* $name:ident;)+) => {
+        $(
+            $(#[$meta])*
+            #[inline]
+            pub fn $name(&self) -> Result<Option<Duration>> {
+                let ms = self.get::<Option<i64>>(stringify!($name), ())?;
+                Ok(ms.filter(|&ms| ms >= 0).map(|ms| Duration::from_millis(ms as u64)))
+            }
+        )+
+    };
+}
+
 impl Fetches {
     /// Executes an internal haproxy sample fetch.
     #[inline]
@@ -19,6 +52,136 @@ impl Fetches {
     pub fn get_str(&self, name: &str, args: impl IntoLuaMulti) -> Result<String> {
         Ok((self.0.call_method::<Option<_>>(name, args)?).unwrap_or_default())
     }
+
+    /// Returns the source IP address of the client of the session.
+    #[inline]
+    pub fn src(&self) -> Result<Option<IpAddr>> {
+        self.get_addr("src")
+    }
+
+    /// Returns the local IP address of the front connection (the address the client connected to).
+    #[inline]
+    pub fn dst(&self) -> Result<Option<IpAddr>> {
+        self.get_addr("dst")
+    }
+
+    /// Returns the value of the request header `name`.
+    ///
+    /// The optional `occ` selects a specific occurrence (negative values count from the last one).
+    /// By default the last occurrence is returned.
+    #[inline]
+    pub fn req_hdr(&self, name: &str, occ: Option<i32>) -> Result<Option<String>> {
+        match occ {
+            Some(occ) => self.get("req_hdr", (name, occ)),
+            None => self.get("req_hdr", name),
+        }
+    }
+
+    /// Returns the value of the response header `name`.
+    ///
+    /// See [`Fetches::req_hdr`] for the meaning of `occ`.
+    #[inline]
+    pub fn res_hdr(&self, name: &str, occ: Option<i32>) -> Result<Option<String>> {
+        match occ {
+            Some(occ) => self.get("res_hdr", (name, occ)),
+            None => self.get("res_hdr", name),
+        }
+    }
+
+    /// Returns the number of occurrences of the request header `name`,
+    /// or the total number of headers if `name` is `None`.
+    #[inline]
+    pub fn hdr_cnt(&self, name: Option<&str>) -> Result<Option<usize>> {
+        match name {
+            Some(name) => self.get("hdr_cnt", name),
+            None => self.get("hdr_cnt", ()),
+        }
+    }
+
+    /// Returns the number of occurrences of the response header `name`,
+    /// or the total number of headers if `name` is `None`.
+    #[inline]
+    pub fn res_hdr_cnt(&self, name: Option<&str>) -> Result<Option<usize>> {
+        match name {
+            Some(name) => self.get("res_hdr_cnt", name),
+            None => self.get("res_hdr_cnt", ()),
+        }
+    }
+
+    /// Returns true when the front connection was made over an SSL/TLS transport layer.
+    #[inline]
+    pub fn ssl_fc(&self) -> Result<Option<bool>> {
+        // Booleans are returned as integers unless `tune.lua.bool-sample-conversion` is `normal`
+        match self.get::<Value>("ssl_fc", ())? {
+            Value::Nil => Ok(None),
+            Value::Integer(i) => Ok(Some(i != 0)),
+            value => Ok(Some(value.as_boolean().unwrap_or_default())),
+        }
+    }
+
+    fetches! {
+        /// Returns the source port of the client of the session.
+        src_port -> u16;
+        /// Returns the local port of the front connection.
+        dst_port -> u16;
+        /// Returns the name of the frontend.
+        fe_name -> String;
+        /// Returns the name of the backend.
+        be_name -> String;
+        /// Returns the name of the server.
+        srv_name -> String;
+        /// Returns the HTTP method of the request.
+        method -> String;
+        /// Returns the path of the request (without the query string).
+        path -> String;
+        /// Returns the query string of the request (without the leading `?`).
+        query -> String;
+        /// Returns the URL of the request as presented in the request line.
+        url -> String;
+        /// Returns the HTTP version of the request (eg. "1.1").
+        req_ver -> String;
+        /// Returns the HTTP status code of the response.
+        status -> u16;
+        /// Returns the Server Name Indication TLS extension sent by the client.
+        ssl_fc_sni -> String;
+        /// Returns the name of the SSL/TLS protocol used by the front connection.
+        ssl_fc_protocol -> String;
+        /// Returns the unique ID of the request (see `unique-id-format`).
+        unique_id -> String;
+    }
+
+    timer_fetches! {
+        /// Returns the time spent waiting for a complete request from the client (`TR`).
+        req_timer_hdr;
+        /// Returns the idle time before the request (`Ti`).
+        req_timer_idle;
+        /// Returns the time spent waiting in the queues (`Tw`).
+        req_timer_queue;
+        /// Returns the total time to get the client request (`Tq`).
+        req_timer_tq;
+        /// Returns the time spent to establish the connection to the server (`Tc`).
+        bc_timer_connect;
+        /// Returns the time spent waiting for the server response headers (`Tr`).
+        res_timer_hdr;
+        /// Returns the time spent to transfer the response data (`Td`).
+        res_timer_data;
+        /// Returns the time spent on the SSL/TLS handshake of the front connection (`Th`).
+        fc_timer_handshake;
+        /// Returns the total session duration (`Tt`).
+        fc_timer_total;
+        /// Returns the active time of the transaction (`Ta`).
+        txn_timer_total;
+        /// Returns the time spent on the transaction excluding the idle time (`Tu`).
+        txn_timer_user;
+    }
+
+    fn get_addr(&self, name: &str) -> Result<Option<IpAddr>> {
+        match self.get::<Option<String>>(name, ())? {
+            Some(addr) => (addr.parse().map(Some))
+                .map_err(|err| mlua::Error::runtime(format!("invalid address '{addr}': {err}"))),
+            None => Ok(None),
+        }
+    }
 }
 
 impl FromLua for Fetches {
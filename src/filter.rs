@@ -14,6 +14,7 @@ impl FilterMethod {
     pub const HTTP_HEADERS: u8 = 0b00000100;
     pub const HTTP_PAYLOAD: u8 = 0b00001000;
     pub const HTTP_END: u8 = 0b00010000;
+    pub const TCP_PAYLOAD: u8 = 0b00100000;
 
     pub const ALL: u8 = u8::MAX;
 }
@@ -38,9 +39,17 @@ impl FilterResult {
     }
 }
 
-/// A flag corresponding to the filter flag FLT_CFG_FL_HTX.
-/// When it is set for a filter, it means the filter is able to filter HTTP streams.
-const FLT_CFG_FL_HTX: u8 = 1;
+/// Represents configuration flags of [`UserFilter`].
+pub struct FilterFlags;
+
+impl FilterFlags {
+    /// A flag corresponding to the filter flag FLT_CFG_FL_HTX.
+    /// When it is set for a filter, it means the filter is able to filter HTTP streams.
+    /// Without it, the filter can be used only in TCP proxies.
+    pub const HTX: u8 = 0b00000001;
+
+    pub const NONE: u8 = 0;
+}
 
 /// A trait that defines all required callback functions to implement filters.
 pub trait UserFilter: Sized {
@@ -48,6 +57,10 @@ pub trait UserFilter: Sized {
     /// By default ALL
     const METHODS: u8 = FilterMethod::ALL;
 
+    /// Sets configuration flags of this filter.
+    /// By default HTX (HTTP streams)
+    const FLAGS: u8 = FilterFlags::HTX;
+
     /// Continue execution if a filter callback returns an error.
     const CONTINUE_IF_ERROR: bool = true;
 
@@ -84,6 +97,27 @@ pub trait UserFilter: Sized {
         Ok(FilterResult::Continue)
     }
 
+    /// Called during the TCP payload analysis on the channel `chn`.
+    ///
+    /// The incoming data available to the filter starts at `offset` and is `len` bytes long.
+    /// Channel methods are relative to the filter data, so `offset` is only informative.
+    ///
+    /// Returns the number of bytes to forward, or `None` to forward all of them.
+    ///
+    /// HAProxy calls this callback only for non-HTTP streams, whatever the filter flags are.
+    /// HTTP streams use [`UserFilter::http_payload`] instead.
+    fn tcp_payload(
+        &mut self,
+        lua: &Lua,
+        txn: Txn,
+        chn: Channel,
+        offset: usize,
+        len: usize,
+    ) -> Result<Option<usize>> {
+        let _ = (lua, txn, chn, offset, len);
+        Ok(None)
+    }
+
     //
     // HAProxy provided methods
     //
@@ -124,7 +158,7 @@ where
 
         // Attributes
         class.raw_set("id", type_name::<T>())?;
-        class.raw_set("flags", FLT_CFG_FL_HTX)?;
+        class.raw_set("flags", T::FLAGS)?;
 
         //
         // Methods
@@ -193,23 +227,7 @@ where
                     let ud = t.raw_get::<AnyUserData>(1)?;
                     let mut this = ud.borrow_mut::<Self>()?;
                     txn.r#priv = Value::Table(t);
-                    let mut res = Variadic::new();
-                    match this.http_payload(lua, txn, msg) {
-                        Ok(Some(len)) => {
-                            res.push(len.into_lua(lua)?);
-                        }
-                        Ok(None) => {}
-                        Err(err) if T::CONTINUE_IF_ERROR => {
-                            if let Ok(core) = Core::new(lua) {
-                                let _ = core.log(
-                                    LogLevel::Err,
-                                    format!("Filter '{}': {}", type_name::<T>(), err),
-                                );
-                            }
-                        }
-                        Err(err) => return Err(err),
-                    };
-                    Ok(res)
+                    Self::process_payload_result(lua, this.http_payload(lua, txn, msg))
                 })?,
             )?;
         }
@@ -226,6 +244,27 @@ where
             )?;
         }
 
+        if T::METHODS & FilterMethod::TCP_PAYLOAD != 0 {
+            class.raw_set(
+                "tcp_payload",
+                lua.create_function(
+                    |lua, (t, mut txn, chn, window): (Table, Txn, Channel, Variadic<usize>)| {
+                        let ud = t.raw_get::<AnyUserData>(1)?;
+                        let mut this = ud.borrow_mut::<Self>()?;
+                        txn.r#priv = Value::Table(t);
+                        // Fallback to the whole filter data if HAProxy does not pass the window
+                        let offset = window.first().copied().unwrap_or(0);
+                        let len = match window.get(1) {
+                            Some(&len) => len,
+                            None => chn.input()?,
+                        };
+                        let res = this.tcp_payload(lua, txn, chn, offset, len);
+                        Self::process_payload_result(lua, res)
+                    },
+                )?,
+            )?;
+        }
+
         Ok(class)
     }
 
@@ -245,6 +284,26 @@ where
             Err(err) => Err(err),
         }
     }
+
+    fn process_payload_result(lua: &Lua, res: Result<Option<usize>>) -> Result<Variadic<Value>> {
+        let mut ret = Variadic::new();
+        match res {
+            Ok(Some(len)) => {
+                ret.push(len.into_lua(lua)?);
+            }
+            Ok(None) => {}
+            Err(err) if T::CONTINUE_IF_ERROR => {
+                if let Ok(core) = Core::new(lua) {
+                    let _ = core.log(
+                        LogLevel::Err,
+                        format!("Filter '{}': {}", type_name::<T>(), err),
+                    );
+                }
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(ret)
+    }
 }
 
 impl<T> UserData for UserFilterWrapper<T> where T: UserFilter + 'static {}
@@ -2,13 +2,18 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 
 use mlua::{
-    FromLua, IntoLua, Lua, ObjectLike, Result, String as LuaString, Table, TablePairs, Value,
+    BString, FromLua, IntoLua, Lua, ObjectLike, Result, String as LuaString, Table, TablePairs,
+    Value,
 };
 
 /// The "Http" class contain all the HTTP manipulation functions.
 #[derive(Clone)]
 pub struct Http(Table);
 
+/// A table of HTTP header fields, indexed by the lower case header name.
+///
+/// Lookups are case-insensitive. Values can be read as raw bytes (see [`Headers::get_raw`]),
+/// which is safe for non UTF-8 header fields.
 #[derive(Clone)]
 pub struct Headers(Table);
 
@@ -159,6 +164,75 @@ impl Headers {
         }
         Ok(None)
     }
+
+    /// Returns all header fields by `name` as raw bytes.
+    #[inline]
+    pub fn get_raw(&self, name: &str) -> Result<Vec<BString>> {
+        self.get(name)
+    }
+
+    /// Returns first header field by `name` as raw bytes.
+    #[inline]
+    pub fn get_first_raw(&self, name: &str) -> Result<Option<BString>> {
+        self.get_first(name)
+    }
+
+    /// Returns true if the header field `name` is present.
+    #[inline]
+    pub fn contains(&self, name: &str) -> Result<bool> {
+        let name = name.to_ascii_lowercase();
+        Ok(self.0.get::<Option<Table>>(name)?.is_some())
+    }
+
+    /// Returns the number of header fields, counting every occurrence of repeated headers.
+    pub fn len(&self) -> Result<usize> {
+        let mut len = 0;
+        for item in self.0.pairs::<LuaString, Table>() {
+            len += item?.1.pairs::<Value, Value>().count();
+        }
+        Ok(len)
+    }
+
+    /// Returns true if there are no header fields.
+    #[inline]
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns all elements of the comma-separated list header `name` (eg. `Cache-Control`).
+    ///
+    /// Elements are trimmed and empty elements are skipped.
+    /// Commas inside quoted strings are not taken into account.
+    pub fn get_list(&self, name: &str) -> Result<Vec<BString>> {
+        let values = self.get_raw(name)?;
+        Ok(values
+            .iter()
+            .flat_map(|value| split_list(value))
+            .map(BString::from)
+            .collect())
+    }
+
+    /// Returns the elements of the comma-separated list header `name` with their weight (q-value),
+    /// sorted by descending weight (eg. `Accept-Encoding: gzip;q=0.5, br` returns `br` then `gzip`).
+    ///
+    /// Parameters are removed from the elements. Elements without a weight have the weight 1,
+    /// elements with an invalid weight are skipped.
+    pub fn get_weighted(&self, name: &str) -> Result<Vec<(BString, f32)>> {
+        let values = self.get_raw(name)?;
+        let items = weighted_list(values.iter().map(|value| &value[..]));
+        Ok((items.into_iter())
+            .map(|(value, q)| (BString::from(value), q))
+            .collect())
+    }
+
+    /// Returns true if the `value` has the highest non-zero weight in the list header `name`.
+    ///
+    /// Values are compared case-insensitively. Other values with the same weight are allowed.
+    pub fn prefers(&self, name: &str, value: &str) -> Result<bool> {
+        let values = self.get_raw(name)?;
+        let items = weighted_list(values.iter().map(|value| &value[..]));
+        Ok(is_preferred(&items, value.as_bytes()))
+    }
 }
 
 impl Deref for Headers {
@@ -177,6 +251,45 @@ impl FromLua for Headers {
     }
 }
 
+// Splits the comma-separated list into trimmed non-empty elements
+fn split_list(value: &[u8]) -> impl Iterator<Item = &[u8]> {
+    (value.split(|&b| b == b','))
+        .map(|item| item.trim_ascii())
+        .filter(|item| !item.is_empty())
+}
+
+// Splits the list element into the value and its weight (q-value)
+fn parse_weighted(item: &[u8]) -> Option<(&[u8], f32)> {
+    let mut params = item.split(|&b| b == b';').map(|param| param.trim_ascii());
+    let value = params.next()?;
+    let mut q = 1.0;
+    for param in params {
+        if let Some(qval) = (param.strip_prefix(b"q=")).or_else(|| param.strip_prefix(b"Q=")) {
+            q = match std::str::from_utf8(qval).ok()?.parse::<f32>() {
+                Ok(q) if (0.0..=1.0).contains(&q) => q, // q-values over 1 are unacceptable
+                _ => return None,
+            };
+        }
+    }
+    Some((value, q))
+}
+
+// Parses the list header values into elements sorted by descending weight.
+// Elements with the same weight keep their order, invalid elements are skipped.
+fn weighted_list<'a>(values: impl Iterator<Item = &'a [u8]>) -> Vec<(&'a [u8], f32)> {
+    let mut items = (values.flat_map(split_list))
+        .filter_map(parse_weighted)
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| b.1.total_cmp(&a.1));
+    items
+}
+
+// Returns true if the `value` has the highest non-zero weight in the sorted `items`
+fn is_preferred(items: &[(&[u8], f32)], value: &[u8]) -> bool {
+    let max_q = items.first().map(|(_, q)| *q).unwrap_or_default();
+    (items.iter()).any(|(v, q)| *q > 0. && *q == max_q && v.eq_ignore_ascii_case(value))
+}
+
 pub struct HeaderPairs<'a, V: FromLua> {
     pairs: TablePairs<'a, LuaString, Table>,
     phantom: PhantomData<V>,
@@ -203,3 +316,73 @@ impl<V: FromLua> Iterator for HeaderPairs<'_, V> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_list() {
+        let items = split_list(b" gzip, ,br ,,  deflate ,").collect::<Vec<_>>();
+        assert_eq!(items, [&b"gzip"[..], b"br", b"deflate"]);
+        assert_eq!(split_list(b"").count(), 0);
+        assert_eq!(split_list(b" , ,").count(), 0);
+    }
+
+    #[test]
+    fn test_parse_weighted() {
+        assert_eq!(parse_weighted(b"br"), Some((&b"br"[..], 1.0)));
+        assert_eq!(parse_weighted(b"br;q=0.5"), Some((&b"br"[..], 0.5)));
+        assert_eq!(
+            parse_weighted(b"br ; level=1 ; Q=0.2"),
+            Some((&b"br"[..], 0.2))
+        );
+        assert_eq!(parse_weighted(b"br;q=0"), Some((&b"br"[..], 0.0)));
+        assert_eq!(parse_weighted(b"br;q=1"), Some((&b"br"[..], 1.0)));
+
+        // Invalid q-values
+        assert_eq!(parse_weighted(b"br;q=1.5"), None);
+        assert_eq!(parse_weighted(b"br;q=-0.1"), None);
+        assert_eq!(parse_weighted(b"br;q=abc"), None);
+        assert_eq!(parse_weighted(b"br;q="), None);
+    }
+
+    #[test]
+    fn test_weighted_list() {
+        let values = [
+            &b"gzip;q=0.5, br"[..],
+            b"identity;q=0, ,deflate;q=x",
+            b"zstd;q=0.5",
+        ];
+        let items = weighted_list(values.into_iter());
+        assert_eq!(
+            items,
+            [
+                (&b"br"[..], 1.0),
+                (b"gzip", 0.5),
+                (b"zstd", 0.5),
+                (b"identity", 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_preferred() {
+        // Ties
+        let items = weighted_list([&b"gzip;q=0.8, br;q=0.8, deflate;q=0.1"[..]].into_iter());
+        assert!(is_preferred(&items, b"gzip"));
+        assert!(is_preferred(&items, b"BR"));
+        assert!(!is_preferred(&items, b"deflate"));
+        assert!(!is_preferred(&items, b"zstd"));
+
+        // Zero weight is never preferred
+        let items = weighted_list([&b"br;q=0"[..]].into_iter());
+        assert!(!is_preferred(&items, b"br"));
+
+        // Invalid and empty elements
+        let items = weighted_list([&b"br;q=2, ,gzip;q=0.1"[..]].into_iter());
+        assert!(!is_preferred(&items, b"br"));
+        assert!(is_preferred(&items, b"gzip"));
+        assert!(!is_preferred(&[], b"br"));
+    }
+}
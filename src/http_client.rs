@@ -0,0 +1,175 @@
+use std::ops::Deref;
+use std::time::Duration;
+
+use mlua::{BString, FromLua, IntoLua, Lua, Result, String as LuaString, Table, Value, WeakLua};
+
+#[cfg(feature = "async")]
+use crate::r#async::call_method_yielding;
+use crate::Headers;
+
+/// The "HTTPClient" class allows to send HTTP requests using HAProxy internal HTTP client.
+///
+/// Sending a request waits for the response, which yields in HAProxy, so it is asynchronous
+/// and can be used only in local async functions (see [`create_local_async_function`]),
+/// eg. registered with [`Core::register_local_async_action`] or [`Core::register_local_async_task`].
+///
+/// This is HAProxy >=2.5 feature.
+///
+/// [`create_local_async_function`]: crate::create_local_async_function
+/// [`Core::register_local_async_action`]: crate::Core::register_local_async_action
+/// [`Core::register_local_async_task`]: crate::Core::register_local_async_task
+#[derive(Clone)]
+pub struct HttpClient {
+    class: Table,
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    lua: WeakLua,
+}
+
+/// HTTP methods supported by [`HttpClient`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HttpClientMethod {
+    Get,
+    Head,
+    Put,
+    Post,
+    Delete,
+}
+
+impl HttpClientMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HttpClientMethod::Get => "get",
+            HttpClientMethod::Head => "head",
+            HttpClientMethod::Put => "put",
+            HttpClientMethod::Post => "post",
+            HttpClientMethod::Delete => "delete",
+        }
+    }
+}
+
+/// A request sent by [`HttpClient`].
+#[derive(Debug, Clone)]
+pub struct HttpClientRequest {
+    method: HttpClientMethod,
+    url: String,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Option<Vec<u8>>,
+    timeout: Option<Duration>,
+    dst: Option<String>,
+}
+
+/// A response received by [`HttpClient`].
+pub struct HttpClientResponse {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Headers,
+    pub body: BString,
+}
+
+impl HttpClient {
+    /// Sends the `request` and returns the received response.
+    #[cfg(feature = "async")]
+    pub async fn send(&self, request: &HttpClientRequest) -> Result<HttpClientResponse> {
+        let lua = self.lua.upgrade();
+        let method = request.method.as_str();
+        let response: Table = call_method_yielding(&lua, &self.class, method, request).await?;
+        Ok(HttpClientResponse {
+            status: response.get("status")?,
+            reason: response.get::<Option<_>>("reason")?.unwrap_or_default(),
+            headers: response.get("headers")?,
+            body: response.get::<Option<_>>("body")?.unwrap_or_default(),
+        })
+    }
+}
+
+impl FromLua for HttpClient {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        Ok(HttpClient {
+            class: Table::from_lua(value, lua)?,
+            lua: lua.weak(),
+        })
+    }
+}
+
+impl Deref for HttpClient {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.class
+    }
+}
+
+impl HttpClientRequest {
+    /// Creates a new request with the `method` to the `url`.
+    pub fn new(method: HttpClientMethod, url: impl Into<String>) -> Self {
+        HttpClientRequest {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+            dst: None,
+        }
+    }
+
+    /// Appends an HTTP header field `name` with `value` to the request.
+    pub fn header(mut self, name: &str, value: impl AsRef<[u8]>) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.headers.push((name, value.as_ref().to_vec()));
+        self
+    }
+
+    /// Sets the request body.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Sets the timeout for the whole request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Overrides the destination address (eg. "127.0.0.1:8080" or "unix@/run/app.sock").
+    ///
+    /// The `url` is still used for the `Host` header and the request path.
+    pub fn dst(mut self, dst: impl Into<String>) -> Self {
+        self.dst = Some(dst.into());
+        self
+    }
+}
+
+impl IntoLua for &HttpClientRequest {
+    fn into_lua(self, lua: &Lua) -> Result<Value> {
+        let params = lua.create_table()?;
+        params.raw_set("url", &*self.url)?;
+        if !self.headers.is_empty() {
+            let headers = lua.create_table()?;
+            for (name, value) in &self.headers {
+                let values = match headers.raw_get::<Option<Table>>(&**name)? {
+                    Some(values) => values,
+                    None => {
+                        let values = lua.create_table()?;
+                        headers.raw_set(&**name, &values)?;
+                        values
+                    }
+                };
+                values.raw_push(LuaString::wrap(value))?;
+            }
+            params.raw_set("headers", headers)?;
+        }
+        if let Some(body) = &self.body {
+            params.raw_set("body", LuaString::wrap(body))?;
+        }
+        if let Some(timeout) = self.timeout {
+            params.raw_set("timeout", timeout.as_millis() as u64)?;
+        }
+        if let Some(dst) = &self.dst {
+            params.raw_set("dst", &**dst)?;
+        }
+        Ok(Value::Table(params))
+    }
+}
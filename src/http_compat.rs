@@ -0,0 +1,301 @@
+use ::http::header::{HeaderMap, HeaderName, HeaderValue};
+use ::http::{request, response, Method, Request, Response, StatusCode, Uri, Version};
+use mlua::{Result, String as LuaString};
+
+use crate::{Headers, Http, HttpMessage, Txn};
+
+impl Headers {
+    /// Converts the headers into an [`http::HeaderMap`].
+    pub fn to_header_map(&self) -> Result<HeaderMap> {
+        let mut map = HeaderMap::new();
+        for item in self.pairs::<LuaString>() {
+            let (name, values) = item?;
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| {
+                mlua::Error::runtime(format!("invalid header name '{name}': {err}"))
+            })?;
+            for value in values {
+                let value = HeaderValue::from_bytes(&value.as_bytes()).map_err(|err| {
+                    mlua::Error::runtime(format!("invalid value of header '{name}': {err}"))
+                })?;
+                map.append(&name, value);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl Txn {
+    /// Returns a snapshot of the request start-line and headers.
+    ///
+    /// Modifications of the returned parts are not applied to the request,
+    /// use [`Http::apply_request_parts`] for that.
+    pub fn req_parts(&self) -> Result<request::Parts> {
+        let method = self.f.method()?;
+        let uri = self.f.url()?;
+        let (method, uri) = method.zip(uri).ok_or_else(|| {
+            mlua::Error::runtime("request is not available in the current context")
+        })?;
+        let version = self.f.req_ver()?;
+        let headers = self.http()?.req_get_headers()?.to_header_map()?;
+        request_parts(&method, &uri, version.as_deref(), headers)
+    }
+
+    /// Returns a snapshot of the response status and headers.
+    ///
+    /// Modifications of the returned parts are not applied to the response,
+    /// use [`Http::apply_response_parts`] for that.
+    pub fn res_parts(&self) -> Result<response::Parts> {
+        let status = self.f.status()?.ok_or_else(|| {
+            mlua::Error::runtime("response is not available in the current context")
+        })?;
+        let version = self.f.get::<Option<String>>("res_ver", ())?;
+        let headers = self.http()?.res_get_headers()?.to_header_map()?;
+        response_parts(status, version.as_deref(), headers)
+    }
+}
+
+impl Http {
+    /// Replaces the request headers with the `headers`.
+    ///
+    /// Only the header fields that differ from the current ones are rewritten.
+    pub fn req_set_headers(&self, headers: &HeaderMap) -> Result<()> {
+        let current = self.req_get_headers()?.to_header_map()?;
+        replace_headers(
+            &current,
+            headers,
+            |name| self.req_del_header(name),
+            |name, value| self.req_set_header(name, LuaString::wrap(value)),
+            |name, value| self.req_add_header(name, LuaString::wrap(value)),
+        )
+    }
+
+    /// Replaces the response headers with the `headers`.
+    ///
+    /// Only the header fields that differ from the current ones are rewritten.
+    pub fn res_set_headers(&self, headers: &HeaderMap) -> Result<()> {
+        let current = self.res_get_headers()?.to_header_map()?;
+        replace_headers(
+            &current,
+            headers,
+            |name| self.res_del_header(name),
+            |name, value| self.res_set_header(name, LuaString::wrap(value)),
+            |name, value| self.res_add_header(name, LuaString::wrap(value)),
+        )
+    }
+
+    /// Applies the method, URI and headers of the `parts` to the request.
+    ///
+    /// The HTTP version cannot be changed and is ignored.
+    pub fn apply_request_parts(&self, parts: &request::Parts) -> Result<()> {
+        self.req_set_method(parts.method.as_str())?;
+        self.req_set_uri(&parts.uri.to_string())?;
+        self.req_set_headers(&parts.headers)
+    }
+
+    /// Applies the status and headers of the `parts` to the response.
+    ///
+    /// The HTTP version cannot be changed and is ignored.
+    pub fn apply_response_parts(&self, parts: &response::Parts) -> Result<()> {
+        self.res_set_status(parts.status.as_u16(), None)?;
+        self.res_set_headers(&parts.headers)
+    }
+}
+
+impl HttpMessage {
+    /// Returns a snapshot of the request start-line and headers.
+    pub fn request_parts(&self) -> Result<request::Parts> {
+        let stline = self.get_stline()?;
+        let method: String = stline.get("method")?;
+        let uri: String = stline.get("uri")?;
+        let version: Option<String> = stline.get("version")?;
+        let headers = self.get_headers()?.to_header_map()?;
+        request_parts(&method, &uri, version.as_deref(), headers)
+    }
+
+    /// Returns a snapshot of the response status and headers.
+    pub fn response_parts(&self) -> Result<response::Parts> {
+        let stline = self.get_stline()?;
+        let status: u16 = stline.get("code")?;
+        let version: Option<String> = stline.get("version")?;
+        let headers = self.get_headers()?.to_header_map()?;
+        response_parts(status, version.as_deref(), headers)
+    }
+
+    /// Replaces the headers of the HTTP message with the `headers`.
+    ///
+    /// Only the header fields that differ from the current ones are rewritten.
+    pub fn set_headers(&self, headers: &HeaderMap) -> Result<()> {
+        let current = self.get_headers()?.to_header_map()?;
+        replace_headers(
+            &current,
+            headers,
+            |name| self.del_header(name),
+            |name, value| self.set_header(name, value),
+            |name, value| self.add_header(name, value),
+        )
+    }
+
+    /// Applies the method, URI and headers of the `parts` to the HTTP request message.
+    ///
+    /// The HTTP version cannot be changed and is ignored.
+    pub fn apply_request_parts(&self, parts: &request::Parts) -> Result<()> {
+        self.set_method(parts.method.as_str())?;
+        self.set_uri(&parts.uri.to_string())?;
+        self.set_headers(&parts.headers)
+    }
+
+    /// Applies the status and headers of the `parts` to the HTTP response message.
+    ///
+    /// The HTTP version cannot be changed and is ignored.
+    pub fn apply_response_parts(&self, parts: &response::Parts) -> Result<()> {
+        self.set_status(parts.status.as_u16(), None)?;
+        self.set_headers(&parts.headers)
+    }
+}
+
+fn request_parts(
+    method: &str,
+    uri: &str,
+    version: Option<&str>,
+    headers: HeaderMap,
+) -> Result<request::Parts> {
+    let (mut parts, ()) = Request::new(()).into_parts();
+    parts.method = Method::from_bytes(method.as_bytes())
+        .map_err(|err| mlua::Error::runtime(format!("invalid method '{method}': {err}")))?;
+    parts.uri = (uri.parse::<Uri>())
+        .map_err(|err| mlua::Error::runtime(format!("invalid uri '{uri}': {err}")))?;
+    parts.version = parse_version(version)?;
+    parts.headers = headers;
+    Ok(parts)
+}
+
+fn response_parts(
+    status: u16,
+    version: Option<&str>,
+    headers: HeaderMap,
+) -> Result<response::Parts> {
+    let (mut parts, ()) = Response::new(()).into_parts();
+    parts.status = StatusCode::from_u16(status)
+        .map_err(|err| mlua::Error::runtime(format!("invalid status '{status}': {err}")))?;
+    parts.version = parse_version(version)?;
+    parts.headers = headers;
+    Ok(parts)
+}
+
+// Parses HTTP version in the form returned by HAProxy (eg. "1.1" or "HTTP/1.1")
+fn parse_version(version: Option<&str>) -> Result<Version> {
+    let Some(version) = version else {
+        return Ok(Version::default());
+    };
+    match version.strip_prefix("HTTP/").unwrap_or(version) {
+        "0.9" => Ok(Version::HTTP_09),
+        "1.0" => Ok(Version::HTTP_10),
+        "1.1" => Ok(Version::HTTP_11),
+        "2" | "2.0" => Ok(Version::HTTP_2),
+        "3" | "3.0" => Ok(Version::HTTP_3),
+        _ => Err(mlua::Error::runtime(format!(
+            "unsupported HTTP version '{version}'"
+        ))),
+    }
+}
+
+// Rewrites header fields that differ between `current` and `headers`
+fn replace_headers(
+    current: &HeaderMap,
+    headers: &HeaderMap,
+    del: impl Fn(&str) -> Result<()>,
+    set: impl Fn(&str, &[u8]) -> Result<()>,
+    add: impl Fn(&str, &[u8]) -> Result<()>,
+) -> Result<()> {
+    for name in current.keys() {
+        if !headers.contains_key(name) {
+            del(name.as_str())?;
+        }
+    }
+    for name in headers.keys() {
+        let values = headers.get_all(name);
+        if current.get_all(name).iter().eq(values.iter()) {
+            continue;
+        }
+        for (i, value) in values.iter().enumerate() {
+            match i {
+                0 => set(name.as_str(), value.as_bytes())?,
+                _ => add(name.as_str(), value.as_bytes())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version(None).unwrap(), Version::HTTP_11);
+        assert_eq!(parse_version(Some("1.0")).unwrap(), Version::HTTP_10);
+        assert_eq!(parse_version(Some("HTTP/1.1")).unwrap(), Version::HTTP_11);
+        assert_eq!(parse_version(Some("2")).unwrap(), Version::HTTP_2);
+        assert_eq!(parse_version(Some("HTTP/2.0")).unwrap(), Version::HTTP_2);
+        assert_eq!(parse_version(Some("3.0")).unwrap(), Version::HTTP_3);
+        assert_eq!(parse_version(Some("0.9")).unwrap(), Version::HTTP_09);
+        assert!(parse_version(Some("1.2")).is_err());
+        assert!(parse_version(Some("HTTP/")).is_err());
+        assert!(parse_version(Some("")).is_err());
+    }
+
+    fn header_map(headers: &[(&str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for &(name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).unwrap();
+            map.append(name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    // Returns the list of operations made by `replace_headers`
+    fn replace(current: &[(&str, &str)], headers: &[(&str, &str)]) -> Vec<String> {
+        let ops = RefCell::new(Vec::new());
+        let record = |op: String| {
+            ops.borrow_mut().push(op);
+            Ok(())
+        };
+        replace_headers(
+            &header_map(current),
+            &header_map(headers),
+            |name| record(format!("del {name}")),
+            |name, value| record(format!("set {name}: {}", String::from_utf8_lossy(value))),
+            |name, value| record(format!("add {name}: {}", String::from_utf8_lossy(value))),
+        )
+        .unwrap();
+        ops.into_inner()
+    }
+
+    #[test]
+    fn test_replace_headers() {
+        // Nothing changed
+        let headers = [("host", "example.com"), ("accept", "a"), ("accept", "b")];
+        assert!(replace(&headers, &headers).is_empty());
+
+        // Removed, changed and added fields
+        let ops = replace(
+            &[("host", "example.com"), ("x-old", "1"), ("accept", "a")],
+            &[("host", "example.com"), ("accept", "b"), ("x-new", "2")],
+        );
+        assert_eq!(ops, ["del x-old", "set accept: b", "set x-new: 2"]);
+
+        // Multiple values are rewritten as a whole
+        let ops = replace(
+            &[("accept", "a"), ("accept", "b")],
+            &[("accept", "b"), ("accept", "a"), ("accept", "c")],
+        );
+        assert_eq!(ops, ["set accept: b", "add accept: a", "add accept: c"]);
+
+        // Removed values of a field still present
+        let ops = replace(&[("accept", "a"), ("accept", "b")], &[("accept", "a")]);
+        assert_eq!(ops, ["set accept: a"]);
+    }
+}
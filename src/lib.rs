@@ -7,34 +7,73 @@
 //! [Lua API]: http://www.arpalert.org/src/haproxy-lua-api/2.2/index.html
 //! [mlua]: https://crates.io/crates/mlua
 
+mod action_args;
+mod applet_http;
+mod applet_tcp;
 #[cfg(feature = "async")]
 mod r#async;
+#[cfg(feature = "async")]
+mod async_filter;
+#[cfg(feature = "async")]
+mod async_service;
+mod body_filter;
 mod channel;
+mod cli;
 mod converters;
 mod core;
 mod event_sub;
+mod extensions;
 mod fetches;
 mod filter;
 mod http;
+mod http_client;
+#[cfg(feature = "http")]
+mod http_compat;
 mod http_message;
 mod listener;
+mod map;
+mod patref;
 mod proxy;
+mod queue;
+mod regex;
+mod reply;
+mod serde;
 mod server;
+mod socket;
 mod stick_table;
 mod txn;
+mod var;
 
+pub use crate::action_args::ActionArgs;
+pub use crate::applet_http::AppletHttp;
+pub use crate::applet_tcp::AppletTcp;
+pub use crate::body_filter::{BodyFilter, BodyTransformer};
 pub use crate::channel::Channel;
+pub use crate::cli::CliWriter;
 pub use crate::converters::Converters;
-pub use crate::core::{Action, Core, LogLevel, ServiceMode, Time};
+pub use crate::core::{Action, ActionResult, Core, LogLevel, ServiceMode, Time};
 pub use crate::event_sub::EventSub;
+pub use crate::extensions::Extensions;
 pub use crate::fetches::Fetches;
-pub use crate::filter::{FilterMethod, FilterResult, UserFilter};
+pub use crate::filter::{FilterFlags, FilterMethod, FilterResult, UserFilter};
 pub use crate::http::{Headers, Http};
+pub use crate::http_client::{HttpClient, HttpClientMethod, HttpClientRequest, HttpClientResponse};
 pub use crate::http_message::HttpMessage;
+pub use crate::map::{Map, MatchMethod};
+pub use crate::patref::Patref;
 pub use crate::proxy::Proxy;
+pub use crate::queue::Queue;
+pub use crate::regex::Regex;
+pub use crate::reply::Reply;
 pub use crate::server::Server;
+pub use crate::socket::{ReceivePattern, Socket};
 pub use crate::stick_table::StickTable;
 pub use crate::txn::Txn;
+pub use crate::var::{Var, VarFormat, VarScope, VarStore};
 
 #[cfg(feature = "async")]
-pub use crate::r#async::{create_async_function, runtime};
+pub use crate::async_filter::{AsyncUserFilter, FilterFuture, FilterTarget};
+#[cfg(feature = "async")]
+pub use crate::async_service::{AsyncRequest, RequestBody, ResponseSink};
+#[cfg(feature = "async")]
+pub use crate::r#async::{create_async_function, create_local_async_function, runtime};
@@ -0,0 +1,102 @@
+use std::ops::Deref;
+
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value};
+
+/// The "Map" class allows to do some lookups in HAProxy maps.
+///
+/// The declared maps can be modified during the runtime through the HAProxy management socket.
+#[derive(Clone)]
+pub struct Map(Table);
+
+/// A method used to match the keys of a [`Map`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MatchMethod {
+    /// Exact string match.
+    Str,
+    /// Prefix match.
+    Beg,
+    /// Substring match.
+    Sub,
+    /// Subdir match (slash-delimited).
+    Dir,
+    /// Domain match (dot-delimited).
+    Dom,
+    /// Suffix match.
+    End,
+    /// Regular expression match.
+    Reg,
+    /// IPv4 or IPv6 address match (with optional netmask).
+    Ip,
+    /// Integer match.
+    Int,
+}
+
+impl MatchMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MatchMethod::Str => "str",
+            MatchMethod::Beg => "beg",
+            MatchMethod::Sub => "sub",
+            MatchMethod::Dir => "dir",
+            MatchMethod::Dom => "dom",
+            MatchMethod::End => "end",
+            MatchMethod::Reg => "reg",
+            MatchMethod::Ip => "ip",
+            MatchMethod::Int => "int",
+        }
+    }
+}
+
+impl Map {
+    /// Creates and loads a map from the `file` using the match `method`.
+    ///
+    /// The map file must be loaded during the initialization (eg. in the module entrypoint
+    /// or [`Core::register_init`]), as HAProxy does not allow file access at runtime.
+    ///
+    /// [`Core::register_init`]: crate::Core::register_init
+    pub fn new(lua: &Lua, file: &str, method: MatchMethod) -> Result<Self> {
+        let class: Table = lua.globals().get("Map")?;
+        let method: Value = class.get(format!("_{}", method.as_str()))?;
+        class.call_function("new", (file, method))
+    }
+
+    /// Performs a lookup in the map and returns the matched value.
+    ///
+    /// Returns `None` if no match was found.
+    #[inline]
+    pub fn lookup(&self, key: &str) -> Result<Option<String>> {
+        self.0.call_method("lookup", key)
+    }
+
+    /// Performs a lookup in the map and returns the matched value.
+    ///
+    /// Returns an empty string if no match was found.
+    #[inline]
+    pub fn slookup(&self, key: &str) -> Result<String> {
+        self.0.call_method("slookup", key)
+    }
+}
+
+impl FromLua for Map {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(Map(class))
+    }
+}
+
+impl IntoLua for Map {
+    #[inline]
+    fn into_lua(self, _: &Lua) -> Result<Value> {
+        Ok(Value::Table(self.0))
+    }
+}
+
+impl Deref for Map {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
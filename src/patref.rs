@@ -0,0 +1,112 @@
+use std::ops::Deref;
+
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value};
+
+/// The "Patref" class allows to manipulate pattern references (maps and ACLs).
+///
+/// Updates can be made atomic: [`Patref::prepare`] creates a new pending version,
+/// which is filled using the same functions and then atomically applied with [`Patref::commit`]
+/// or discarded with [`Patref::giveup`].
+///
+/// This is HAProxy >=3.1 feature.
+#[derive(Clone)]
+pub struct Patref(Table);
+
+impl Patref {
+    /// Returns the name of the pattern reference.
+    #[inline]
+    pub fn get_name(&self) -> Result<String> {
+        self.0.call_method("get_name", ())
+    }
+
+    /// Returns true if the pattern reference is used by maps, false if by ACLs.
+    #[inline]
+    pub fn is_map(&self) -> Result<bool> {
+        self.0.call_method("is_map", ())
+    }
+
+    /// Creates a new pending version of the pattern reference.
+    ///
+    /// All the following changes are applied to the pending version until it is
+    /// committed or given up. Any previous pending version is discarded.
+    #[inline]
+    pub fn prepare(&self) -> Result<()> {
+        self.0.call_method("prepare", ())
+    }
+
+    /// Atomically replaces the current version with the pending one.
+    #[inline]
+    pub fn commit(&self) -> Result<()> {
+        self.0.call_method("commit", ())
+    }
+
+    /// Discards the pending version.
+    #[inline]
+    pub fn giveup(&self) -> Result<()> {
+        self.0.call_method("giveup", ())
+    }
+
+    /// Adds a new `key` with optional `value` (maps only) to the pattern reference.
+    #[inline]
+    pub fn add(&self, key: &str, value: Option<&str>) -> Result<()> {
+        self.0.call_method("add", (key, value))
+    }
+
+    /// Adds multiple key/value `entries` to the map pattern reference at once.
+    ///
+    /// HAProxy receives the entries as a Lua table, so if a key is repeated, only its last value is added.
+    #[inline]
+    pub fn add_bulk(&self, entries: &[(&str, &str)]) -> Result<()> {
+        self.0.call_method("add_bulk", BulkEntries(entries))
+    }
+
+    /// Adds multiple `keys` to the ACL pattern reference at once.
+    #[inline]
+    pub fn add_bulk_keys(&self, keys: &[&str]) -> Result<()> {
+        self.0.call_method("add_bulk", keys.to_vec())
+    }
+
+    /// Sets the `value` of the existing `key` (maps only).
+    ///
+    /// If `force` is true, the `key` is added if it does not exist.
+    #[inline]
+    pub fn set(&self, key: &str, value: &str, force: bool) -> Result<()> {
+        self.0.call_method("set", (key, value, force))
+    }
+
+    /// Deletes all entries matching the `key` from the pattern reference.
+    #[inline]
+    pub fn del(&self, key: &str) -> Result<()> {
+        self.0.call_method("del", key)
+    }
+}
+
+impl FromLua for Patref {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(Patref(class))
+    }
+}
+
+impl Deref for Patref {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// Key/value entries converted to a Lua table without intermediate allocations
+struct BulkEntries<'a>(&'a [(&'a str, &'a str)]);
+
+impl IntoLua for BulkEntries<'_> {
+    fn into_lua(self, lua: &Lua) -> Result<Value> {
+        let table = lua.create_table_with_capacity(0, self.0.len())?;
+        for &(key, value) in self.0 {
+            table.raw_set(key, value)?;
+        }
+        Ok(Value::Table(table))
+    }
+}
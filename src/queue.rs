@@ -0,0 +1,101 @@
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value, WeakLua};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[cfg(feature = "async")]
+use crate::r#async::call_method_yielding;
+use crate::serde::{Deserialized, Serialized};
+
+/// The "Queue" class allows to pass data between tasks, actions and services.
+///
+/// Items are stored as Lua values, converted from and to `T` using serde.
+///
+/// Waiting for an item ([`Queue::pop_wait`]) yields in HAProxy, so it is asynchronous and can be
+/// used only in local async functions, eg. registered with [`Core::register_local_async_task`].
+///
+/// [`Core::register_local_async_task`]: crate::Core::register_local_async_task
+pub struct Queue<T> {
+    class: Table,
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    lua: WeakLua,
+    _phantom: PhantomData<fn(T) -> T>,
+}
+
+impl<T> Queue<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Returns the number of items in the queue.
+    #[inline]
+    pub fn size(&self) -> Result<usize> {
+        self.class.call_method("size", ())
+    }
+
+    /// Pushes the `item` at the end of the queue.
+    #[inline]
+    pub fn push(&self, item: &T) -> Result<bool> {
+        self.class.call_method("push", Serialized(item))
+    }
+
+    /// Removes and returns the first item from the queue.
+    ///
+    /// Returns `None` if the queue is empty.
+    #[inline]
+    pub fn pop(&self) -> Result<Option<T>> {
+        let item = self
+            .class
+            .call_method::<Option<Deserialized<T>>>("pop", ())?;
+        Ok(item.map(|item| item.0))
+    }
+
+    /// Removes and returns the first item from the queue, waiting for it if the queue is empty.
+    #[cfg(feature = "async")]
+    pub async fn pop_wait(&self) -> Result<T> {
+        let lua = self.lua.upgrade();
+        let item =
+            call_method_yielding::<Deserialized<T>>(&lua, &self.class, "pop_wait", ()).await?;
+        Ok(item.0)
+    }
+}
+
+impl<T> Clone for Queue<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Queue {
+            class: self.class.clone(),
+            lua: self.lua.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> FromLua for Queue<T> {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(Queue {
+            class,
+            lua: lua.weak(),
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T> IntoLua for Queue<T> {
+    #[inline]
+    fn into_lua(self, _: &Lua) -> Result<Value> {
+        Ok(Value::Table(self.class))
+    }
+}
+
+impl<T> Deref for Queue<T> {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.class
+    }
+}
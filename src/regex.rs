@@ -0,0 +1,71 @@
+use std::ops::Deref;
+
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value};
+
+/// The "Regex" class allows to use HAProxy regular expressions (PCRE when available).
+///
+/// Compiling a regex is relatively costly, so it should be done once during initialization
+/// and reused later from actions, filters, etc.
+#[derive(Clone)]
+pub struct Regex(Table);
+
+impl Regex {
+    /// Compiles the regular expression `pattern`.
+    ///
+    /// Returns an error with the failure reason if the `pattern` is invalid.
+    pub fn new(lua: &Lua, pattern: &str, case_sensitive: bool) -> Result<Self> {
+        let class: Table = lua.globals().get("Regex")?;
+        let (ok, regex): (bool, Value) = class.call_function("new", (pattern, case_sensitive))?;
+        if !ok {
+            let reason = regex.to_string()?;
+            return Err(mlua::Error::runtime(format!(
+                "cannot compile regex '{pattern}': {reason}"
+            )));
+        }
+        Regex::from_lua(regex, lua)
+    }
+
+    /// Returns true if the regex matches the `input`.
+    #[inline]
+    pub fn exec(&self, input: &str) -> Result<bool> {
+        self.0.call_method("exec", input)
+    }
+
+    /// Executes the regex against the `input` and returns the captured substrings.
+    ///
+    /// The first element is the whole match, followed by the capture groups.
+    /// Returns `None` if the regex does not match.
+    #[inline]
+    pub fn r#match(&self, input: &str) -> Result<Option<Vec<String>>> {
+        let (ok, matches): (bool, Option<Vec<String>>) = self.0.call_method("match", input)?;
+        Ok(if ok {
+            Some(matches.unwrap_or_default())
+        } else {
+            None
+        })
+    }
+}
+
+impl FromLua for Regex {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(Regex(class))
+    }
+}
+
+impl IntoLua for Regex {
+    #[inline]
+    fn into_lua(self, _: &Lua) -> Result<Value> {
+        Ok(Value::Table(self.0))
+    }
+}
+
+impl Deref for Regex {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -0,0 +1,76 @@
+use mlua::{IntoLua, Lua, Result, String as LuaString, Table, Value};
+
+/// An HTTP reply used to terminate a transaction with [`Txn::reply_and_done`].
+///
+/// [`Txn::reply_and_done`]: crate::Txn::reply_and_done
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    reason: Option<String>,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl Default for Reply {
+    fn default() -> Self {
+        Reply::new(200)
+    }
+}
+
+impl Reply {
+    /// Creates a new reply with the `status` code.
+    pub fn new(status: u16) -> Self {
+        Reply {
+            status,
+            reason: None,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Sets the status code and optional `reason`.
+    /// If no custom reason is provided, it will be generated from the status.
+    pub fn status(mut self, status: u16, reason: Option<&str>) -> Self {
+        self.status = status;
+        self.reason = reason.map(|r| r.to_string());
+        self
+    }
+
+    /// Appends an HTTP header field `name` with `value`.
+    pub fn header(mut self, name: &str, value: impl AsRef<[u8]>) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.headers.push((name, value.as_ref().to_vec()));
+        self
+    }
+
+    /// Sets the reply body.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+impl IntoLua for Reply {
+    fn into_lua(self, lua: &Lua) -> Result<Value> {
+        let reply = lua.create_table()?;
+        reply.raw_set("status", self.status)?;
+        if let Some(reason) = self.reason {
+            reply.raw_set("reason", reason)?;
+        }
+        let headers = lua.create_table()?;
+        for (name, value) in self.headers {
+            let values = match headers.raw_get::<Option<Table>>(&*name)? {
+                Some(values) => values,
+                None => {
+                    let values = lua.create_table()?;
+                    headers.raw_set(name, &values)?;
+                    values
+                }
+            };
+            values.raw_push(LuaString::wrap(value))?;
+        }
+        reply.raw_set("headers", headers)?;
+        reply.raw_set("body", LuaString::wrap(self.body))?;
+        Ok(Value::Table(reply))
+    }
+}
@@ -0,0 +1,23 @@
+use mlua::{FromLua, IntoLua, Lua, LuaSerdeExt, Result, Value};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Converts a reference to a serializable value into Lua
+pub(crate) struct Serialized<'a, T>(pub(crate) &'a T);
+
+impl<T: Serialize> IntoLua for Serialized<'_, T> {
+    #[inline]
+    fn into_lua(self, lua: &Lua) -> Result<Value> {
+        lua.to_value(self.0)
+    }
+}
+
+// Converts a Lua value into a deserializable value
+pub(crate) struct Deserialized<T>(pub(crate) T);
+
+impl<T: DeserializeOwned> FromLua for Deserialized<T> {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        lua.from_value(value).map(Deserialized)
+    }
+}
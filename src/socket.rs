@@ -0,0 +1,166 @@
+use std::ops::Deref;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use mlua::String as LuaString;
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value, WeakLua};
+
+#[cfg(feature = "async")]
+use crate::r#async::call_method_yielding;
+
+/// The "Socket" class provides access to HAProxy non-blocking TCP sockets.
+///
+/// Connecting, sending and receiving wait for the network, which yields in HAProxy.
+/// These functions are asynchronous and can be used only in local async functions
+/// (see [`create_local_async_function`]), eg. registered with [`Core::register_local_async_action`]
+/// or [`Core::register_local_async_task`].
+///
+/// [`create_local_async_function`]: crate::create_local_async_function
+/// [`Core::register_local_async_action`]: crate::Core::register_local_async_action
+/// [`Core::register_local_async_task`]: crate::Core::register_local_async_task
+#[derive(Clone)]
+pub struct Socket {
+    class: Table,
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    lua: WeakLua,
+}
+
+/// A pattern used by [`Socket::receive`] to read data.
+#[derive(Debug, Copy, Clone)]
+pub enum ReceivePattern {
+    /// Reads a line of text terminated by a LF character (CR characters are ignored).
+    Line,
+    /// Reads from the socket until the connection is closed.
+    All,
+    /// Reads a fixed number of bytes.
+    Bytes(usize),
+}
+
+impl Socket {
+    /// Closes the socket.
+    #[inline]
+    pub fn close(&self) -> Result<()> {
+        self.class.call_method("close", ())
+    }
+
+    /// Connects the socket to the remote host `address`.
+    ///
+    /// The `port` may be omitted if the address already contains it (eg. "127.0.0.1:8080").
+    #[cfg(feature = "async")]
+    pub async fn connect(&self, address: &str, port: Option<u16>) -> Result<()> {
+        let res = match port {
+            Some(port) => self.call_yielding("connect", (address, port)).await?,
+            None => self.call_yielding("connect", address).await?,
+        };
+        Self::check_result::<Value>(res).map(|_| ())
+    }
+
+    /// Same as [`Socket::connect`] but establishes a TLS connection.
+    #[cfg(feature = "async")]
+    pub async fn connect_ssl(&self, address: &str, port: Option<u16>) -> Result<()> {
+        let res = match port {
+            Some(port) => self.call_yielding("connect_ssl", (address, port)).await?,
+            None => self.call_yielding("connect_ssl", address).await?,
+        };
+        Self::check_result::<Value>(res).map(|_| ())
+    }
+
+    /// Returns the address of the remote side of the connection (eg. "127.0.0.1:8080").
+    #[inline]
+    pub fn getpeername(&self) -> Result<Option<String>> {
+        self.class.call_method("getpeername", ())
+    }
+
+    /// Returns the local address of the connection.
+    #[inline]
+    pub fn getsockname(&self) -> Result<Option<String>> {
+        self.class.call_method("getsockname", ())
+    }
+
+    /// Reads data from the socket according to the `pattern`.
+    ///
+    /// The optional `prefix` is concatenated to the beginning of any received data.
+    #[cfg(feature = "async")]
+    pub async fn receive(
+        &self,
+        pattern: ReceivePattern,
+        prefix: Option<&[u8]>,
+    ) -> Result<LuaString> {
+        let prefix = prefix.map(LuaString::wrap);
+        Self::check_result(self.call_yielding("receive", (pattern, prefix)).await?)
+    }
+
+    /// Sends the `data` through the socket.
+    ///
+    /// Returns the number of bytes sent.
+    #[cfg(feature = "async")]
+    pub async fn send(&self, data: impl AsRef<[u8]>) -> Result<usize> {
+        Self::check_result(self.call_yielding("send", LuaString::wrap(data)).await?)
+    }
+
+    /// Changes the timeout values for the socket.
+    ///
+    /// The timeout applies to connect, send and receive operations.
+    #[inline]
+    pub fn settimeout(&self, timeout: Duration) -> Result<()> {
+        self.class.call_method("settimeout", timeout.as_secs_f64())
+    }
+
+    #[cfg(feature = "async")]
+    async fn call_yielding<T: FromLua>(
+        &self,
+        name: &str,
+        args: impl mlua::IntoLuaMulti,
+    ) -> Result<(Option<T>, Option<String>)> {
+        call_method_yielding(&self.lua.upgrade(), &self.class, name, args).await
+    }
+
+    // Converts the `nil, err` convention to `Result`
+    fn check_result<T>((value, err): (Option<T>, Option<String>)) -> Result<T> {
+        match value {
+            Some(value) => Ok(value),
+            None => {
+                let err = err.unwrap_or_else(|| "unknown error".into());
+                Err(mlua::Error::runtime(format!("socket error: {err}")))
+            }
+        }
+    }
+}
+
+impl FromLua for Socket {
+    #[inline]
+    fn from_lua(value: Value, lua: &Lua) -> Result<Self> {
+        let class = Table::from_lua(value, lua)?;
+        Ok(Socket {
+            class,
+            lua: lua.weak(),
+        })
+    }
+}
+
+impl IntoLua for Socket {
+    #[inline]
+    fn into_lua(self, _: &Lua) -> Result<Value> {
+        Ok(Value::Table(self.class))
+    }
+}
+
+impl Deref for Socket {
+    type Target = Table;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.class
+    }
+}
+
+impl IntoLua for ReceivePattern {
+    #[inline]
+    fn into_lua(self, lua: &Lua) -> Result<Value> {
+        match self {
+            ReceivePattern::Line => "*l".into_lua(lua),
+            ReceivePattern::All => "*a".into_lua(lua),
+            ReceivePattern::Bytes(n) => n.into_lua(lua),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::ops::Deref;
 
-use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value};
+use mlua::{FromLua, IntoLua, Lua, ObjectLike, Result, Table, Value, WeakLua};
 
-use crate::{Converters, Fetches, Http, HttpMessage, LogLevel};
+use crate::{Converters, Extensions, Fetches, Http, HttpMessage, LogLevel, Reply};
 
 /// The txn class contain all the functions relative to the http or tcp transaction.
 #[derive(Clone)]
@@ -11,9 +11,26 @@ pub struct Txn {
     pub c: Converters,
     pub f: Fetches,
     pub(crate) r#priv: Value,
+    lua: WeakLua,
 }
 
 impl Txn {
+    /// Returns a [`Converters`] object which always returns strings.
+    ///
+    /// Converters that fail return an empty string instead of `nil`.
+    #[inline]
+    pub fn sc(&self) -> Result<Converters> {
+        self.class.get("sc")
+    }
+
+    /// Returns a [`Fetches`] object which always returns strings.
+    ///
+    /// Sample fetches that fail return an empty string instead of `nil`.
+    #[inline]
+    pub fn sf(&self) -> Result<Fetches> {
+        self.class.get("sf")
+    }
+
     /// Returns an HTTP class object.
     #[inline]
     pub fn http(&self) -> Result<Http> {
@@ -56,6 +73,15 @@ impl Txn {
         self.class.call_method("set_priv", val)
     }
 
+    /// Returns typed Rust values attached to the current transaction.
+    ///
+    /// Unlike `set_priv`, values are not converted to Lua and several actions can share them
+    /// without key collisions. Values may be lost after a Lua error, see [`Extensions`] for details.
+    #[inline]
+    pub fn extensions(&self) -> Result<Extensions> {
+        Extensions::current(&self.lua.upgrade())
+    }
+
     /// Returns data stored in the variable `name`.
     #[inline]
     pub fn get_var<R: FromLua>(&self, name: &str) -> Result<R> {
@@ -80,12 +106,76 @@ impl Txn {
         self.class.call_method("unset_var", name)
     }
 
+    /// Immediately stops the current transaction processing.
+    ///
+    /// HAProxy terminates the transaction by raising an error, so this function always
+    /// returns `Err` which must be propagated (eg. with `?`) back to HAProxy.
+    #[inline]
+    pub fn done(&self) -> Result<()> {
+        self.class.call_method("done", ())
+    }
+
+    /// Sends the `reply` to the client and stops the current transaction processing.
+    ///
+    /// Like [`Txn::done`], the returned error must be propagated back to HAProxy.
+    #[inline]
+    pub fn reply_and_done(&self, reply: Reply) -> Result<()> {
+        let reply: Table = self.class.call_method("reply", reply)?;
+        self.class.call_method("done", reply)
+    }
+
     /// Changes the log level of the current request.
     /// The `level` must be an integer between 0 and 7.
     #[inline]
     pub fn set_loglevel(&self, level: LogLevel) -> Result<()> {
         self.class.call_method("set_loglevel", level)
     }
+
+    /// Sets the TOS or DSCP field value of packets sent to the client.
+    #[inline]
+    pub fn set_tos(&self, tos: u8) -> Result<()> {
+        self.class.call_method("set_tos", tos)
+    }
+
+    /// Sets the Netfilter MARK on all packets sent to the client.
+    #[inline]
+    pub fn set_mark(&self, mark: u32) -> Result<()> {
+        self.class.call_method("set_mark", mark)
+    }
+
+    /// Sets the TOS or DSCP field value of packets sent to the client on the front connection.
+    #[inline]
+    pub fn set_fc_tos(&self, tos: u8) -> Result<()> {
+        self.class.call_method("set_fc_tos", tos)
+    }
+
+    /// Sets the Netfilter MARK on all packets sent to the client on the front connection.
+    #[inline]
+    pub fn set_fc_mark(&self, mark: u32) -> Result<()> {
+        self.class.call_method("set_fc_mark", mark)
+    }
+
+    /// Sets the priority class of the current request in the server queue.
+    /// The `class` must be between -2047 and 2047, lower values are dequeued first.
+    #[inline]
+    pub fn set_priority_class(&self, class: i32) -> Result<()> {
+        if !(-2047..=2047).contains(&class) {
+            let msg = format!("priority class {class} is out of range [-2047, 2047]");
+            return Err(mlua::Error::runtime(msg));
+        }
+        self.class.call_method("set_priority_class", class)
+    }
+
+    /// Sets the priority offset of the current request in the server queue.
+    /// The `offset` must be between -524287 and 524287, lower values are dequeued first.
+    #[inline]
+    pub fn set_priority_offset(&self, offset: i32) -> Result<()> {
+        if !(-524287..=524287).contains(&offset) {
+            let msg = format!("priority offset {offset} is out of range [-524287, 524287]");
+            return Err(mlua::Error::runtime(msg));
+        }
+        self.class.call_method("set_priority_offset", offset)
+    }
 }
 
 impl FromLua for Txn {
@@ -97,6 +187,7 @@ impl FromLua for Txn {
             f: class.get("f")?,
             class,
             r#priv: Value::Nil,
+            lua: lua.weak(),
         })
     }
 }
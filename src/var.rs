@@ -0,0 +1,273 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use mlua::{FromLua, IntoLua, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::serde::{Deserialized, Serialized};
+use crate::{AppletHttp, AppletTcp, Core, Txn};
+
+/// The scope of an HAProxy variable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VarScope {
+    /// Variable is shared with the whole process.
+    Proc,
+    /// Variable is shared with the whole session.
+    Sess,
+    /// Variable is shared with the transaction (request and response).
+    Txn,
+    /// Variable is shared only during request processing.
+    Req,
+    /// Variable is shared only during response processing.
+    Res,
+    /// Variable is declared and visible only during health checks.
+    Check,
+}
+
+impl VarScope {
+    /// Returns the scope prefix used in variable names.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VarScope::Proc => "proc",
+            VarScope::Sess => "sess",
+            VarScope::Txn => "txn",
+            VarScope::Req => "req",
+            VarScope::Res => "res",
+            VarScope::Check => "check",
+        }
+    }
+}
+
+impl fmt::Display for VarScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VarScope {
+    type Err = mlua::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "proc" => Ok(VarScope::Proc),
+            "sess" => Ok(VarScope::Sess),
+            "txn" => Ok(VarScope::Txn),
+            "req" => Ok(VarScope::Req),
+            "res" => Ok(VarScope::Res),
+            "check" => Ok(VarScope::Check),
+            _ => Err(mlua::Error::runtime(format!(
+                "unknown variable scope '{s}' (expected proc, sess, txn, req, res or check)"
+            ))),
+        }
+    }
+}
+
+/// How values of a [`Var`] are stored in HAProxy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VarFormat {
+    /// Value is converted to a native HAProxy sample (string, integer, boolean).
+    ///
+    /// Only scalar values are supported. This is the only format readable from HAProxy configuration.
+    Native,
+    /// Value is serialized to a JSON string.
+    #[cfg(feature = "json")]
+    Json,
+    /// Value is serialized to a MessagePack binary.
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+}
+
+/// A typed handle to an HAProxy variable.
+///
+/// Values are converted from and to `T` using serde, according to the [`VarFormat`].
+pub struct Var<T> {
+    scope: VarScope,
+    name: String,
+    format: VarFormat,
+    _phantom: PhantomData<fn(T) -> T>,
+}
+
+/// An object that holds HAProxy variables.
+pub trait VarStore {
+    /// Scopes of variables available in this context.
+    const SCOPES: &'static [VarScope];
+
+    #[doc(hidden)]
+    fn load_var<R: FromLua>(&self, name: &str) -> Result<R>;
+
+    #[doc(hidden)]
+    fn store_var(&self, name: &str, val: impl IntoLua) -> Result<()>;
+
+    #[doc(hidden)]
+    fn clear_var(&self, name: &str) -> Result<()>;
+}
+
+impl<T> Var<T> {
+    /// Creates a new variable handle with the `scope` and `name` (without the scope prefix).
+    pub fn new(scope: VarScope, name: impl Into<String>) -> Self {
+        Var {
+            scope,
+            name: name.into(),
+            format: VarFormat::Native,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new variable handle from the full variable name (eg. "txn.start_time").
+    pub fn parse(name: &str) -> Result<Self> {
+        let (scope, name) = name.split_once('.').ok_or_else(|| {
+            mlua::Error::runtime(format!("variable '{name}' must be prefixed with a scope"))
+        })?;
+        Ok(Self::new(scope.parse()?, name))
+    }
+
+    /// Sets the storage format of the variable.
+    pub fn with_format(mut self, format: VarFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns the variable scope.
+    pub fn scope(&self) -> VarScope {
+        self.scope
+    }
+
+    /// Returns the full variable name, including the scope prefix.
+    pub fn full_name(&self) -> String {
+        format!("{}.{}", self.scope, self.name)
+    }
+
+    fn check_scope<S: VarStore>(&self) -> Result<String> {
+        if !S::SCOPES.contains(&self.scope) {
+            let scopes = S::SCOPES.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            return Err(mlua::Error::runtime(format!(
+                "variable '{}' cannot be used here: scope '{}' is not available (expected {})",
+                self.full_name(),
+                self.scope,
+                scopes.join(", "),
+            )));
+        }
+        Ok(self.full_name())
+    }
+}
+
+impl<T> Var<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Returns the variable value, or `None` if the variable is not set.
+    pub fn get<S: VarStore>(&self, store: &S) -> Result<Option<T>> {
+        let name = self.check_scope::<S>()?;
+        match self.format {
+            VarFormat::Native => {
+                let val = store.load_var::<Option<Deserialized<T>>>(&name)?;
+                Ok(val.map(|val| val.0))
+            }
+            #[cfg(feature = "json")]
+            VarFormat::Json => match store.load_var::<Option<mlua::BString>>(&name)? {
+                Some(data) => serde_json::from_slice(&data).map(Some).map_err(|err| {
+                    mlua::Error::runtime(format!("cannot decode variable '{name}': {err}"))
+                }),
+                None => Ok(None),
+            },
+            #[cfg(feature = "msgpack")]
+            VarFormat::MsgPack => match store.load_var::<Option<mlua::BString>>(&name)? {
+                Some(data) => rmp_serde::from_slice(&data).map(Some).map_err(|err| {
+                    mlua::Error::runtime(format!("cannot decode variable '{name}': {err}"))
+                }),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Sets the variable value.
+    pub fn set<S: VarStore>(&self, store: &S, val: &T) -> Result<()> {
+        let name = self.check_scope::<S>()?;
+        match self.format {
+            VarFormat::Native => store.store_var(&name, Serialized(val)),
+            #[cfg(feature = "json")]
+            VarFormat::Json => {
+                let data = serde_json::to_vec(val).map_err(|err| {
+                    mlua::Error::runtime(format!("cannot encode variable '{name}': {err}"))
+                })?;
+                store.store_var(&name, mlua::String::wrap(data))
+            }
+            #[cfg(feature = "msgpack")]
+            VarFormat::MsgPack => {
+                let data = rmp_serde::to_vec_named(val).map_err(|err| {
+                    mlua::Error::runtime(format!("cannot encode variable '{name}': {err}"))
+                })?;
+                store.store_var(&name, mlua::String::wrap(data))
+            }
+        }
+    }
+
+    /// Unsets the variable.
+    pub fn unset<S: VarStore>(&self, store: &S) -> Result<()> {
+        let name = self.check_scope::<S>()?;
+        store.clear_var(&name)
+    }
+}
+
+impl<T> Clone for Var<T> {
+    fn clone(&self) -> Self {
+        Var {
+            scope: self.scope,
+            name: self.name.clone(),
+            format: self.format,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Var<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (f.debug_struct("Var"))
+            .field("name", &self.full_name())
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+// Health checks have their own context, so `check` scope is not available here
+const STREAM_SCOPES: &[VarScope] = &[
+    VarScope::Proc,
+    VarScope::Sess,
+    VarScope::Txn,
+    VarScope::Req,
+    VarScope::Res,
+];
+
+macro_rules! impl_var_store {
+    ($($ty:ty => $scopes:expr),+ $(,)?) => {
+        $(
+            impl VarStore for $ty {
+                const SCOPES: &'static [VarScope] = $scopes;
+
+                #[inline]
+                fn load_var<R: FromLua>(&self, name: &str) -> Result<R> {
+                    self.get_var(name)
+                }
+
+                #[inline]
+                fn store_var(&self, name: &str, val: impl IntoLua) -> Result<()> {
+                    self.set_var(name, val)
+                }
+
+                #[inline]
+                fn clear_var(&self, name: &str) -> Result<()> {
+                    self.unset_var(name)
+                }
+            }
+        )+
+    };
+}
+
+impl_var_store! {
+    Txn => STREAM_SCOPES,
+    AppletHttp => STREAM_SCOPES,
+    AppletTcp => STREAM_SCOPES,
+    Core<'_> => &[VarScope::Proc],
+}